@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use engine::game::{VirtualKeyCode, WinitInputHelper};
+use crate::script::Controls;
+use crate::state::MenuInput;
+
+///something the player can do, bound to any number of keys and mouse buttons
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Jump,
+    Sprint,
+    Dig,
+    Place,
+    Explode,
+    NextSlot,
+    PrevSlot,
+    Slot(u8),                           //hotbar slot 1 to 9
+    Inventory,
+    GrowBrush,
+    ShrinkBrush,
+    ToggleNoclip,
+    ToggleDebug,
+    ReloadControls,
+    Pause,                              //also goes back out of menus
+    Confirm,                            //picks the selected menu item
+}
+
+impl Action {
+    ///every action, in the order the controls file and menu list them
+    pub fn all() -> impl Iterator<Item=Action> {
+        ACTIONS.iter().map(|(_, action)| *action)
+    }
+
+    ///name of the action in the controls file
+    pub fn name(&self) -> &'static str {
+        ACTIONS.iter().find(|(_, a)| a == self).map(|(name, _)| *name).unwrap_or("")
+    }
+}
+
+///names actions have in the controls file
+const ACTIONS: [(&str, Action); 28] = [
+    ("move_left", Action::MoveLeft),
+    ("move_right", Action::MoveRight),
+    ("move_up", Action::MoveUp),
+    ("move_down", Action::MoveDown),
+    ("jump", Action::Jump),
+    ("sprint", Action::Sprint),
+    ("dig", Action::Dig),
+    ("place", Action::Place),
+    ("explode", Action::Explode),
+    ("next_slot", Action::NextSlot),
+    ("prev_slot", Action::PrevSlot),
+    ("slot_1", Action::Slot(1)),
+    ("slot_2", Action::Slot(2)),
+    ("slot_3", Action::Slot(3)),
+    ("slot_4", Action::Slot(4)),
+    ("slot_5", Action::Slot(5)),
+    ("slot_6", Action::Slot(6)),
+    ("slot_7", Action::Slot(7)),
+    ("slot_8", Action::Slot(8)),
+    ("slot_9", Action::Slot(9)),
+    ("inventory", Action::Inventory),
+    ("grow_brush", Action::GrowBrush),
+    ("shrink_brush", Action::ShrinkBrush),
+    ("toggle_noclip", Action::ToggleNoclip),
+    ("toggle_debug", Action::ToggleDebug),
+    ("reload_controls", Action::ReloadControls),
+    ("pause", Action::Pause),
+    ("confirm", Action::Confirm),
+];
+
+///actions that were renamed, old name then new, so controls files from before still load
+const RENAMED: [(&str, &str); 3] = [
+    ("quit", "pause"),
+    ("next_material", "next_slot"),
+    ("prev_material", "prev_slot"),
+];
+
+///controls.toml as shipped, used when the one on disk wont load
+const DEFAULT_CONTROLS: &str = include_str!("../controls.toml");
+
+///keys that can be bound, named by how they print
+const KEYS: &[VirtualKeyCode] = {
+    use VirtualKeyCode::*;
+    &[
+        Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+        Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+        Left, Up, Right, Down, Back, Return, Space, Tab,
+        LShift, RShift, LControl, RControl, LAlt, RAlt,
+        Minus, Equals, LBracket, RBracket, Comma, Period, Slash, Semicolon, Apostrophe, Backslash, Grave,
+        Delete, Home, End, PageUp, PageDown, Insert,
+    ]
+};
+
+const MOUSE_BUTTONS: [&str; 3] = ["MouseLeft", "MouseRight", "MouseMiddle"];
+
+///a key or mouse button
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Input {
+    Key(VirtualKeyCode),
+    Mouse(usize),
+}
+
+impl std::fmt::Display for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Input::Key(k) => write!(f, "{:?}", k),
+            Input::Mouse(b) => write!(f, "{}", MOUSE_BUTTONS.get(*b).unwrap_or(&"Mouse")),
+        }
+    }
+}
+
+impl std::str::FromStr for Input {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        if let Some(button) = MOUSE_BUTTONS.iter().position(|b| *b == name) {return Ok(Input::Mouse(button))}
+        KEYS.iter().find(|k| format!("{:?}", k) == name).map(|k| Input::Key(*k)).ok_or_else(|| format!("unknown key {}", name))
+    }
+}
+
+///which inputs trigger which actions
+pub struct Bindings {
+    map: HashMap<Action, Vec<Input>>,
+}
+
+impl Bindings {
+    ///loads bindings from a toml file of action = ["Key", ...]
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("couldn't read controls file {}: {}", path, e))?;
+        Bindings::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let file: HashMap<String, Vec<String>> = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut bindings = Bindings {map: HashMap::new()};
+        for (name, inputs) in file {
+            let current = RENAMED.iter().find(|(old, _)| *old == name).map_or(name.as_str(), |(_, new)| *new);
+            let action = ACTIONS.iter().find(|(n, _)| *n == current).map(|(_, a)| *a).ok_or_else(|| format!("unknown action {}", name))?;
+            for input in inputs {
+                bindings.bind(action, input.parse().map_err(|e| format!("{} in {}", e, name))?);
+            }
+        }
+        Ok(bindings)
+    }
+
+    ///writes every binding back to a controls file, headed by the comments the shipped one starts with
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_string()).map_err(|e| format!("couldn't write controls file {}: {}", path, e))
+    }
+
+    ///the bindings the game ships with
+    pub fn defaults() -> Self {
+        Bindings::parse(DEFAULT_CONTROLS).expect("shipped controls.toml has to parse")
+    }
+
+    ///adds another input that triggers action
+    pub fn bind(&mut self, action: Action, input: Input) {
+        let inputs = self.map.entry(action).or_default();
+        if !inputs.contains(&input) {inputs.push(input)}
+    }
+
+    ///unbinds every input from action
+    pub fn clear(&mut self, action: Action) {
+        self.map.remove(&action);
+    }
+
+    ///inputs bound to action
+    pub fn get(&self, action: Action) -> &[Input] {
+        self.map.get(&action).map(|i| &i[..]).unwrap_or(&[])
+    }
+
+    ///if any input bound to action is down
+    pub fn held(&self, action: Action, input: &WinitInputHelper) -> bool {
+        self.get(action).iter().any(|i| match i {
+            Input::Key(k) => input.key_held(*k),
+            Input::Mouse(b) => input.mouse_held(*b),
+        })
+    }
+
+    ///if any input bound to action went down since last update
+    pub fn pressed(&self, action: Action, input: &WinitInputHelper) -> bool {
+        self.get(action).iter().any(|i| match i {
+            Input::Key(k) => input.key_pressed(*k),
+            Input::Mouse(b) => input.mouse_pressed(*b),
+        })
+    }
+
+    ///reads the gameplay controls held right now
+    pub fn controls(&self, input: &WinitInputHelper, cursor: (isize, isize)) -> Controls {
+        let held = |action| self.held(action, input);
+        Controls {
+            left: held(Action::MoveLeft),
+            right: held(Action::MoveRight),
+            up: held(Action::MoveUp),
+            down: held(Action::MoveDown),
+            jump: held(Action::Jump),
+            run: held(Action::Sprint),
+            dig: held(Action::Dig),
+            place: held(Action::Place),
+            explode: held(Action::Explode),
+            next_slot: held(Action::NextSlot),
+            prev_slot: held(Action::PrevSlot),
+            grow_brush: held(Action::GrowBrush),
+            shrink_brush: held(Action::ShrinkBrush),
+            noclip: held(Action::ToggleNoclip),
+            respawn: false,                                                             //comes from the death screen
+            slot: (1..10).find(|n| held(Action::Slot(*n))).unwrap_or(0),
+            scroll: match input.scroll_diff() {                                         //wheel isnt bindable, down moves along the hotbar
+                d if d > 0.0 => -1,
+                d if d < 0.0 => 1,
+                _ => 0,
+            },
+            swap: None,                                                                 //comes from the inventory screen
+            view_distance: 0,                                                           //comes from the settings menu
+            cursor,
+        }
+    }
+
+    ///reads what menus need this update. clicks and typing arent bindable so they come straight from the mouse and keyboard
+    pub fn menu_input(&self, input: &WinitInputHelper, cursor: (isize, isize)) -> MenuInput {
+        let pressed = |action| self.pressed(action, input);
+        MenuInput {
+            up: pressed(Action::MoveUp),
+            down: pressed(Action::MoveDown),
+            left: pressed(Action::MoveLeft),
+            right: pressed(Action::MoveRight),
+            confirm: pressed(Action::Confirm),
+            back: pressed(Action::Pause),
+            inventory: pressed(Action::Inventory),
+            click: input.mouse_pressed(0),
+            cursor,
+            typed: KEYS.iter().filter(|k| input.key_pressed(**k)).filter_map(|k| typed_char(*k)).collect(),
+            erase: input.key_pressed(VirtualKeyCode::Back),
+            pressed: (0..MOUSE_BUTTONS.len()).find(|b| input.mouse_pressed(*b)).map(Input::Mouse)
+                .or_else(|| KEYS.iter().find(|k| input.key_pressed(**k)).map(|k| Input::Key(*k))),
+        }
+    }
+}
+
+impl std::fmt::Display for Bindings {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for line in DEFAULT_CONTROLS.lines().take_while(|l| l.starts_with('#')) {writeln!(f, "{}", line)?}
+        writeln!(f)?;
+        for action in Action::all() {
+            let inputs = self.get(action).iter().map(|i| format!("\"{}\"", i)).collect::<Vec<String>>();
+            writeln!(f, "{} = [{}]", action.name(), inputs.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+///the character a key types, for keys that type a letter, number or space
+fn typed_char(key: VirtualKeyCode) -> Option<char> {
+    let name = format!("{:?}", key);
+    match (key, name.len()) {
+        (VirtualKeyCode::Space, _) => Some(' '),
+        (VirtualKeyCode::Minus, _) => Some('-'),
+        (_, 1) => name.chars().next().map(|c| c.to_ascii_lowercase()),                  //A to Z
+        (_, 4) if name.starts_with("Key") => name.chars().nth(3),                       //Key0 to Key9
+        _ => None,
+    }
+}
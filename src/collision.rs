@@ -0,0 +1,119 @@
+use std::ops::Range;
+use crate::gen::*;
+use crate::material::*;
+
+///how far a swept box got before touching terrain
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Sweep {
+    pub travelled: (isize, isize),      //offset the box can move without overlapping anything
+    pub normal: Option<(isize, isize)>, //side of the box that hit, none if it got all the way
+}
+
+impl Chunk {
+    ///packs which particles collide into one bit each so queries test 64 at a time
+    pub fn with_collision(mut self, registry: &Registry) -> Self {
+        self.collision = vec![0; self.data.len()/64 + 1];                                //a spare word if it divides evenly
+        for i in 0..self.data.len() {
+            if registry.get(self.data[i].material()).collision {self.collision[i/64] |= 1 << (i%64)}
+        }
+        self
+    }
+
+    ///if the particle at inner index collides
+    #[inline]
+    pub fn collides(&self, i: usize) -> bool {
+        self.collision[i/64] >> (i%64) & 1 == 1
+    }
+
+    #[inline]
+    pub fn set_collision(&mut self, i: usize, collides: bool) {
+        if collides {self.collision[i/64] |= 1 << (i%64)}
+        else {self.collision[i/64] &= !(1 << (i%64))}
+    }
+
+    ///if any particle in a range of inner indexes collides
+    #[inline]
+    fn any_collision(&self, range: Range<usize>) -> bool {
+        if range.start >= range.end {return false}
+        let (first, last) = (range.start/64, (range.end-1)/64);
+        let head = !0u64 << (range.start%64);                                           //bits from start to the end of its word
+        let tail = !0u64 >> (63 - (range.end-1)%64);                                    //bits up to the end of the range in its word
+        if first == last {return self.collision[first] & head & tail != 0}
+        self.collision[first] & head != 0
+            || self.collision[first+1..last].iter().any(|w| *w != 0)
+            || self.collision[last] & tail != 0
+    }
+}
+
+impl World {
+    ///if the particle at world coords collides. chunks that arent loaded yet count as solid so nothing falls into them
+    #[inline]
+    pub fn is_solid(&self, coords: (isize, isize), chunk_dim: (usize, usize)) -> bool {
+        let (chunk, (ix, iy)) = World::get_chunk_coords(coords, chunk_dim);
+        match self.data.get(&chunk) {
+            Some(c) => c.collides(iy*chunk_dim.0+ix),
+            None => true,
+        }
+    }
+
+    ///if anything solid is inside the box between two world coords, edges included
+    pub fn aabb_overlaps(&self, min: (isize, isize), max: (isize, isize), chunk_dim: (usize, usize)) -> bool {
+        let mut wy = max.1;
+        while wy >= min.1 {                                                             //walk down the box one band of chunk rows at a time
+            let (_, (_, top)) = World::get_chunk_coords((min.0, wy), chunk_dim);
+            let rows = (chunk_dim.1 - top).min((wy - min.1 + 1) as usize);              //rows left in chunk or box
+            let mut wx = min.0;
+            while wx <= max.0 {                                                         //walk band one chunk span at a time
+                let (chunk, (ix, iy)) = World::get_chunk_coords((wx, wy), chunk_dim);
+                let span = (chunk_dim.0 - ix).min((max.0 - wx + 1) as usize);
+                match self.data.get(&chunk) {                                           //one lookup for the whole span of rows
+                    Some(c) => if (iy..iy+rows).any(|r| c.any_collision(r*chunk_dim.0+ix..r*chunk_dim.0+ix+span)) {return true},
+                    None => return true,
+                }
+                wx += span as isize;
+            }
+            wy -= rows as isize;
+        }
+        false
+    }
+
+    ///moves a box along delta a px at a time until the edge it moves into would overlap something
+    ///only the row or column the box enters is tested, so particles already inside it dont stop it
+    pub fn sweep_aabb(&self, min: (isize, isize), max: (isize, isize), delta: (isize, isize), chunk_dim: (usize, usize)) -> Sweep {
+        let steps = delta.0.abs().max(delta.1.abs());
+        let (sx, sy) = (delta.0.signum(), delta.1.signum());
+        let mut travelled = (0, 0);
+        for i in 1..steps+1 {
+            let target = (delta.0*i/steps, delta.1*i/steps);                            //where a straight line puts the box this step
+            if target.0 != travelled.0 {                                                //move on x first
+                let x = if sx > 0 {max.0 + target.0} else {min.0 + target.0};           //column being entered
+                if self.aabb_overlaps((x, min.1 + travelled.1), (x, max.1 + travelled.1), chunk_dim) {
+                    return Sweep {travelled, normal: Some((-sx, 0))};
+                }
+                travelled.0 = target.0;
+            }
+            if target.1 != travelled.1 {                                                //then y
+                let y = if sy > 0 {max.1 + target.1} else {min.1 + target.1};           //row being entered
+                if self.aabb_overlaps((min.0 + travelled.0, y), (max.0 + travelled.0, y), chunk_dim) {
+                    return Sweep {travelled, normal: Some((0, -sy))};
+                }
+                travelled.1 = target.1;
+            }
+        }
+        Sweep {travelled, normal: None}
+    }
+
+    ///first solid particle on a straight line between two world coords, from included
+    pub fn raycast(&self, from: (isize, isize), to: (isize, isize), chunk_dim: (usize, usize)) -> Option<(isize, isize)> {
+        let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());               //bresenham so no pixel on the line gets skipped
+        let (sx, sy) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+        let (mut x, mut y, mut err) = (from.0, from.1, dx + dy);
+        loop {
+            if self.is_solid((x, y), chunk_dim) {return Some((x, y))}
+            if (x, y) == to {return None}
+            let e2 = 2*err;
+            if e2 >= dy {err += dy; x += sx}
+            if e2 <= dx {err += dx; y += sy}
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+pub const CONFIG_FILE: &str = "config.toml";
+const MIN_SCREEN_DIM: (usize, usize) = (320, 180);  //hud text is drawn up from the bottom so it needs some room
+const CHUNK_SIZES: std::ops::RangeInclusive<usize> = 16..=4096;
+pub const VIEW_DISTANCES: std::ops::RangeInclusive<isize> = 1..=32;
+
+///settings that used to be constants, read from config.toml then overridden on the command line
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub screen_dim: (usize, usize),
+    pub fullscreen: bool,
+    pub fps: u64,                       //fps cap, ticks run at TICK_RATE regardless
+    pub chunk_dim: (usize, usize),      //always square and a power of two so chunk coords are a shift and a mask
+    pub gen_range: isize,               //how many chunks out from the player to keep loaded
+    pub seed: Option<u32>,              //none picks a random one
+    pub debug: bool,                    //if debug can be toggled
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            screen_dim: (1920, 1080),
+            fullscreen: true,
+            fps: 60,
+            chunk_dim: (256, 256),
+            gen_range: 4,
+            seed: None,
+            debug: true,
+        }
+    }
+}
+
+///config.toml as written, anything left out keeps its default
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    resolution: Option<(usize, usize)>,
+    fullscreen: Option<bool>,
+    fps: Option<i64>,
+    chunk_size: Option<i64>,
+    view_distance: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<Seed>,
+    debug: Option<bool>,
+}
+
+///seeds can be written as a number or as any text, which gets hashed
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Seed {
+    Number(i64),
+    Text(String),
+}
+
+impl Config {
+    ///reads a config file. a missing default config file just means defaults, a missing one that was asked for is an error
+    pub fn load(path: &str, required: bool) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Config::parse(&text).map_err(|e| format!("{}: {}", path, e)),
+            Err(e) if required || e.kind() != std::io::ErrorKind::NotFound => Err(format!("couldn't read config {}: {}", path, e)),
+            Err(_) => Ok(Config::default()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let file: ConfigFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut config = Config::default();
+        if let Some(resolution) = file.resolution {config.screen_dim = resolution}
+        if let Some(fullscreen) = file.fullscreen {config.fullscreen = fullscreen}
+        if let Some(fps) = file.fps {config.fps = whole("fps", fps)?}
+        if let Some(size) = file.chunk_size {let size = whole("chunk_size", size)?; config.chunk_dim = (size, size)}
+        if let Some(distance) = file.view_distance {config.gen_range = distance as isize}
+        match file.seed {
+            Some(Seed::Number(n)) => config.seed = Some(whole("seed", n)?),
+            Some(Seed::Text(text)) => config.seed = Some(parse_seed(&text)),
+            None => {},
+        }
+        if let Some(debug) = file.debug {config.debug = debug}
+        config.validate()?;
+        Ok(config)
+    }
+
+    ///writes every setting to a config file, losing any comments in it
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let file = ConfigFile {
+            resolution: Some(self.screen_dim),
+            fullscreen: Some(self.fullscreen),
+            fps: Some(self.fps as i64),
+            chunk_size: Some(self.chunk_dim.0 as i64),
+            view_distance: Some(self.gen_range as i64),
+            seed: self.seed.map(|s| Seed::Number(s as i64)),
+            debug: Some(self.debug),
+        };
+        let text = toml::to_string(&file).map_err(|e| e.to_string())?;
+        std::fs::write(path, text).map_err(|e| format!("couldn't write config {}: {}", path, e))
+    }
+
+    ///overrides settings from command line flags, ignoring flags that arent settings
+    ///    --resolution <w>x<h>  --fullscreen  --windowed  --fps <n>  --chunk-size <n>
+    ///    --view-distance <n>  --seed <number or text>  --debug  --no-debug
+    pub fn apply_args(&mut self, args: &[String]) -> Result<(), String> {
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", flag));
+            match flag.as_str() {
+                "--resolution" => {
+                    let v = value()?;
+                    self.screen_dim = match v.split_once('x').map(|(w, h)| (w.parse(), h.parse())) {
+                        Some((Ok(w), Ok(h))) => (w, h),
+                        _ => return Err(format!("--resolution needs a size like 1280x720, not {}", v)),
+                    };
+                },
+                "--fullscreen" => self.fullscreen = true,
+                "--windowed" => self.fullscreen = false,
+                "--fps" => {let v = value()?; self.fps = v.parse().map_err(|_| format!("--fps needs a number, not {}", v))?},
+                "--chunk-size" => {
+                    let v = value()?;
+                    let size = v.parse().map_err(|_| format!("--chunk-size needs a number, not {}", v))?;
+                    self.chunk_dim = (size, size);
+                },
+                "--view-distance" => {let v = value()?; self.gen_range = v.parse().map_err(|_| format!("--view-distance needs a number, not {}", v))?},
+                "--seed" => self.seed = Some(parse_seed(value()?)),
+                "--debug" => self.debug = true,
+                "--no-debug" => self.debug = false,
+                _ => {},
+            }
+        }
+        self.validate()
+    }
+
+    ///checks every setting is something the game can run with
+    pub fn validate(&self) -> Result<(), String> {
+        let (w, h) = self.screen_dim;
+        if w < MIN_SCREEN_DIM.0 || h < MIN_SCREEN_DIM.1 {
+            return Err(format!("resolution {}x{} is too small, it has to be at least {}x{}", w, h, MIN_SCREEN_DIM.0, MIN_SCREEN_DIM.1));
+        }
+        if self.fps == 0 {return Err("fps has to be at least 1".to_string())}
+        let size = self.chunk_dim.0;
+        if self.chunk_dim.1 != size {return Err(format!("chunks have to be square, not {}x{}", size, self.chunk_dim.1))}
+        if !size.is_power_of_two() {return Err(format!("chunk_size {} has to be a power of two", size))}
+        if !CHUNK_SIZES.contains(&size) {
+            return Err(format!("chunk_size {} has to be between {} and {}", size, CHUNK_SIZES.start(), CHUNK_SIZES.end()));
+        }
+        if !VIEW_DISTANCES.contains(&self.gen_range) {
+            return Err(format!("view_distance {} has to be between {} and {}", self.gen_range, VIEW_DISTANCES.start(), VIEW_DISTANCES.end()));
+        }
+        Ok(())
+    }
+}
+
+///a number of at least 0 that fits in T, named in the error if it doesnt
+fn whole<T: std::convert::TryFrom<i64>>(name: &str, n: i64) -> Result<T, String> {
+    T::try_from(n).map_err(|_| format!("{} {} is out of range", name, n))
+}
+
+///seeds that arent a number are hashed so any word picks a world. fnv-1a
+pub fn parse_seed(text: &str) -> u32 {
+    text.parse().unwrap_or_else(|_| text.bytes().fold(0x811c_9dc5u32, |h, b| (h ^ b as u32).wrapping_mul(0x0100_0193)))
+}
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use crate::gen::*;
+use crate::material::*;
+
+///if offset from the center of an ellipse with half width and height radii is inside it
+pub fn in_ellipse(offset: (isize, isize), radii: (isize, isize)) -> bool {
+    let (dx, dy) = (offset.0 * radii.1, offset.1 * radii.0);
+    dx*dx + dy*dy <= (radii.0 * radii.1).pow(2)
+}
+
+///calls f with world coords of every particle in an ellipse
+fn for_ellipse(center: (isize, isize), radii: (isize, isize), mut f: impl FnMut((isize, isize))) {
+    for dy in -radii.1..radii.1+1 {
+        for dx in -radii.0..radii.0+1 {
+            if in_ellipse((dx, dy), radii) {f((center.0+dx, center.1+dy))}
+        }
+    }
+}
+
+impl World {
+    ///clears particles in an ellipse to air where strength beats their hardness and tier is high enough to dig them
+    ///particles further from the center need more strength, up to double their hardness at the edge
+    ///returns how many of each material were removed and the strength the next particle needs, none if nothing left can be dug
+    pub fn mine(&mut self, center: (isize, isize), radii: (isize, isize), tier: u8, strength: f32, chunk_dim: (usize, usize)) -> (HashMap<MaterialId, usize>, Option<f32>) {
+        let mut removed = HashMap::new();
+        let mut next: Option<f32> = None;
+        for_ellipse(center, radii, |coords| {
+            let p = match self.get_particle(coords, chunk_dim) {Some(p) if p.material() != AIR => p, _ => return};
+            let material = self.registry.get(p.material());
+            if material.tier > tier {return}                                                    //needs a better tool
+            let (dx, dy) = ((coords.0 - center.0) as f32 / (radii.0 as f32 + 1.0), (coords.1 - center.1) as f32 / (radii.1 as f32 + 1.0));
+            let needed = material.hardness as f32 * (1.0 + (dx*dx + dy*dy).sqrt());             //how far out it is relative to the edge
+            if strength < needed {
+                next = Some(next.map_or(needed, |n| n.min(needed)));
+                return
+            }
+            *removed.entry(p.material()).or_insert(0) += 1;
+            self.set_particle(coords, Particle::new(AIR, coords), chunk_dim);
+        });
+        (removed, next)
+    }
+
+    ///fills the air in a circle with up to limit particles of material, skipping anywhere allowed returns false for. returns how many particles were placed
+    pub fn place(&mut self, center: (isize, isize), radius: isize, material: MaterialId, limit: usize, chunk_dim: (usize, usize), allowed: impl Fn((isize, isize)) -> bool) -> usize {
+        let mut placed = 0;
+        for_ellipse(center, (radius, radius), |coords| {
+            if placed < limit && matches!(self.get_particle(coords, chunk_dim), Some(p) if p.material() == AIR) && allowed(coords) {
+                self.set_particle(coords, Particle::new(material, coords), chunk_dim);
+                placed += 1;
+            }
+        });
+        placed
+    }
+
+    ///fills the air in a box between two corners with material, skipping anywhere allowed returns false for. returns how many particles were placed
+    pub fn place_rect(&mut self, min: (isize, isize), max: (isize, isize), material: MaterialId, chunk_dim: (usize, usize), allowed: impl Fn((isize, isize)) -> bool) -> usize {
+        let mut placed = 0;
+        for y in min.1..max.1+1 {
+            for x in min.0..max.0+1 {
+                if matches!(self.get_particle((x, y), chunk_dim), Some(p) if p.material() == AIR) && allowed((x, y)) {
+                    self.set_particle((x, y), Particle::new(material, (x, y)), chunk_dim);
+                    placed += 1;
+                }
+            }
+        }
+        placed
+    }
+}
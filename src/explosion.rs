@@ -0,0 +1,80 @@
+use crate::gen::*;
+use crate::material::*;
+use crate::prefab::cell_hash;
+
+const DEBRIS_FRACTION: f64 = 0.35;  //share of blasted particles thrown as debris, the rest are destroyed
+const DEBRIS_SPEED: f32 = 1.5;      //px per tick thrown per point of power left at the particle
+const DEBRIS_GRAVITY: f32 = 0.4;    //px per tick debris speeds up falling
+const DEBRIS_LIFETIME: u16 = 600;   //ticks before debris still flying is dropped where it is
+
+///a particle thrown out of the grid by an explosion, flying until it lands
+#[derive(Clone, Copy, Debug)]
+pub struct Debris {
+    pub particle: Particle,
+    pub coords: (f32, f32),         //world coords
+    pub velocity: (f32, f32),       //px per tick
+    pub lifetime: u16,              //ticks left before it settles wherever it is
+}
+
+impl World {
+    ///blasts a circle out of the world. power falls off to 0 at the edge and particles go where it beats their hardness
+    ///some of what gets removed is thrown outwards as debris. returns how many particles were removed, anything caught in it is left to Player::blast
+    pub fn explode(&mut self, center: (isize, isize), radius: isize, power: f32, chunk_dim: (usize, usize)) -> usize {
+        let mut removed = 0;
+        for dy in -radius..radius+1 {
+            for dx in -radius..radius+1 {
+                let distance = ((dx*dx + dy*dy) as f32).sqrt();
+                if distance > radius as f32 {continue}
+                let coords = (center.0+dx, center.1+dy);
+                let p = match self.get_particle(coords, chunk_dim) {Some(p) if p.material() != AIR => p, _ => continue};
+                let strength = power * (1.0 - distance/(radius as f32 + 1.0));                     //power left this far out
+                if strength < self.registry.get(p.material()).hardness as f32 {continue}            //too hard to break
+                self.set_particle(coords, Particle::new(AIR, coords), chunk_dim);
+                removed += 1;
+
+                let (roll, jitter) = cell_hash(self.seed, self.tick as usize, coords);             //same blast always throws the same debris
+                if roll >= DEBRIS_FRACTION {continue}
+                let angle = (dy as f32).atan2(dx as f32) + ((jitter & 0xff) as f32 / 255.0 - 0.5);  //outwards from center, spread a little
+                let speed = strength * DEBRIS_SPEED * (0.5 + ((jitter >> 8) & 0xff) as f32 / 255.0);
+                self.debris.push(Debris {
+                    particle: p,
+                    coords: (coords.0 as f32, coords.1 as f32),
+                    velocity: (angle.cos() * speed, angle.sin() * speed),
+                    lifetime: DEBRIS_LIFETIME,
+                });
+            }
+        }
+        removed
+    }
+
+    ///moves flying debris a tick, putting it back into the grid in the last free spot before whatever it hits
+    pub fn update_debris(&mut self, chunk_dim: (usize, usize)) {
+        let debris = std::mem::take(&mut self.debris);
+        for mut d in debris {
+            d.velocity.1 -= DEBRIS_GRAVITY;
+            d.lifetime = d.lifetime.saturating_sub(1);
+            let steps = d.velocity.0.abs().max(d.velocity.1.abs()).ceil().max(1.0);        //move 1px at a time so it cant skip through walls
+            let step = (d.velocity.0 / steps, d.velocity.1 / steps);
+            let mut last = (d.coords.0.round() as isize, d.coords.1.round() as isize);
+            let mut landed = false;
+            for _ in 0..steps as usize {
+                let next = ((d.coords.0 + step.0).round() as isize, (d.coords.1 + step.1).round() as isize);
+                if next != last && !matches!(self.get_particle(next, chunk_dim), Some(p) if p.material() == AIR) {    //hit something or left the loaded world
+                    landed = true;
+                    break;
+                }
+                d.coords = (d.coords.0 + step.0, d.coords.1 + step.1);
+                last = next;
+            }
+            if landed || d.lifetime == 0 {self.land(d.particle, last, chunk_dim)}
+            else {self.debris.push(d)}
+        }
+    }
+
+    ///puts debris back into the grid if theres room for it, otherwise its lost
+    fn land(&mut self, particle: Particle, coords: (isize, isize), chunk_dim: (usize, usize)) {
+        if matches!(self.get_particle(coords, chunk_dim), Some(p) if p.material() == AIR) {
+            self.set_particle(coords, Particle::new(particle.material(), coords), chunk_dim);
+        }
+    }
+}
@@ -1,81 +1,327 @@
 use rand::Rng;
-use noise::{NoiseFn, Perlin, Seedable};
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasherDefault, Hasher};
+use std::sync::Arc;
+use crate::material::*;
+use crate::save::{SaveDir, SaveError};
+use crate::generator::{GeneratorKind, WorldGenerator};
+use crate::pool::ChunkPool;
+use crate::explosion::Debris;
+
+const EVICT_MARGIN: isize = 1;  //how many chunks past gen range to keep before evicting
+const PLACEHOLDER: [[u8;4];2] = [[48,48,56,255], [60,60,70,255]];  //stripes drawn where chunks are still generating
+
+
+pub type ChunkMap<V> = HashMap<(isize, isize), V, BuildHasherDefault<ChunkHasher>>;
+
+///cheap multiply-rotate hasher for chunk coords
+///the simulation looks chunks up for nearly every particle it touches and SipHash dominated the profile
+#[derive(Default)]
+pub struct ChunkHasher(u64);
+
+impl Hasher for ChunkHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|b| self.write_u64(*b as u64));
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.0 = (self.0.rotate_left(5) ^ i).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+
+    #[inline]
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
 
 
 pub struct World {
-    pub data: Vec<Vec<Chunk>>,
-    _generator: Perlin,
+    pub data: ChunkMap<Chunk>,                  //resident chunks keyed by world chunk coords
+    pub registry: Registry,                     //properties of every material particles can be
+    generator: Arc<dyn WorldGenerator>,         //fills in chunks that were never saved
+    pool: Option<ChunkPool>,                    //generates in the background if started, otherwise chunks generate when asked for
+    pub generator_kind: GeneratorKind,
     pub seed: u32,
-    _gen_range: isize,
+    pub tick: u64,                              //how many simulation ticks have run
+    pub debris: Vec<Debris>,                    //particles flying outside the grid
+    pub gen_range: isize,                       //how many chunks out from the player to keep loaded
+    pub save: Option<SaveDir>,                  //where modified chunks are written, none for throwaway worlds
 }
 
 impl World {
-    pub fn new_perlin(chunk_dim: (usize, usize), seed: &mut u32, set_seed: bool, gen_range: isize) -> Self{
-        if !set_seed {                                                                                  //if set seed flag not set
+    ///creates an empty world. chunks are loaded or generated by update_chunks
+    ///a saved world keeps the seed and generator it was created with
+    pub fn new(seed: &mut u32, set_seed: bool, gen_range: isize, registry: Registry, generator: GeneratorKind, mut save: Option<SaveDir>) -> Result<Self, String> {
+        let mut generator_kind = generator;
+        if let Some(save) = &mut save {save.set_materials(registry.count())}
+        if let Some(saved) = save.as_ref().and_then(|s| s.seed) {                                       //if loading a saved world use its seed
+            *seed = saved;
+        } else if !set_seed {                                                                           //if set seed flag not set
             *seed = rand::thread_rng().gen();                                                           //gen random seed
         }
-        let generator = Perlin::new().set_seed(*seed);                                                  //return Perlin generator
+        if let Some(saved) = save.as_ref().and_then(|s| s.generator.as_ref()) {                         //and its generator
+            generator_kind = saved.parse()?;
+        }
+        let generator = generator_kind.build(*seed, &registry)?.into();
+
+        Ok(Self {
+            data: ChunkMap::default(),                                                                  //chunks are generated on demand
+            registry,
+            generator,
+            pool: None,
+            generator_kind,
+            seed: *seed,
+            tick: 0,
+            debris: Vec::new(),
+            gen_range,
+            save,
+        })
+    }
+
+    ///moves loading and generating chunks onto background worker threads, nearest chunks first
+    ///without workers update_chunks generates everything it needs before returning, which keeps runs repeatable
+    pub fn start_workers(&mut self, threads: usize, chunk_dim: (usize, usize)) {
+        self.pool = Some(ChunkPool::new(threads, self.generator.clone(), self.save.as_ref().map(|s| s.regions()), chunk_dim));
+    }
 
-        let mut data= Vec::new();                                                                       //creates empty vec to hold world
-        for (yi, world_chunk_y) in (gen_range*-1..gen_range+1).rev().enumerate() {                      //for y index, y in gen range counting down
-            data.push(Vec::new());                                                                      //push new row
-            for world_chunk_x in gen_range*-1..gen_range+1 {                                            //for chunk in gen range of row
-                data[yi].push(Chunk::gen_perlin((world_chunk_x, world_chunk_y), generator, chunk_dim)); //gen new perlin chunk and put it there
+    ///if a chunk is waiting on a worker
+    #[inline]
+    pub fn is_pending(&self, chunk_coords: (isize, isize)) -> bool {
+        matches!(&self.pool, Some(pool) if pool.is_pending(chunk_coords))
+    }
+
+    ///where players first appear
+    pub fn spawn_point(&self) -> (isize, isize) {
+        self.generator.spawn_point()
+    }
+
+    ///name of the biome at world x, if the generator has biomes
+    pub fn biome_at(&self, wx: isize) -> Option<&'static str> {
+        self.generator.biome_at(wx)
+    }
+
+    ///splits world coords into the coords of the chunk they fall in and the inner coords within that chunk
+    ///chunk (x,y) spans world x from x*width to right and world y from y*height downwards
+    ///chunk dims are powers of two so this is a shift and a mask instead of the divisions a runtime size would cost
+    #[inline]
+    pub fn get_chunk_coords(coords: (isize, isize), chunk_dim: (usize, usize)) -> ((isize, isize), (usize, usize)) {
+        debug_assert!(chunk_dim.0.is_power_of_two() && chunk_dim.1.is_power_of_two());
+        let (sw, sh) = (chunk_dim.0.trailing_zeros(), chunk_dim.1.trailing_zeros());
+        let (cx, ix) = (coords.0 >> sw, coords.0 as usize & (chunk_dim.0 - 1));         //chunk x counts right, shifting rounds down like div_euclid
+        let (cy, iy) = ((-coords.1) >> sh, (-coords.1) as usize & (chunk_dim.1 - 1));   //inner y counts down so flip before splitting
+        ((cx, -cy), (ix, iy))
+    }
+
+    ///splits an index into chunk data into inner coords, a mask and a shift for the same reason as get_chunk_coords
+    #[inline]
+    pub fn get_inner_coords(i: usize, chunk_dim: (usize, usize)) -> (usize, usize) {
+        (i & (chunk_dim.0 - 1), i >> chunk_dim.0.trailing_zeros())
+    }
+
+    ///world coords of an index into a chunks data
+    #[inline]
+    pub fn get_world_coords(chunk_coords: (isize, isize), i: usize, chunk_dim: (usize, usize)) -> (isize, isize) {
+        let (ix, iy) = World::get_inner_coords(i, chunk_dim);
+        ((chunk_coords.0 << chunk_dim.0.trailing_zeros()) + ix as isize, (chunk_coords.1 << chunk_dim.1.trailing_zeros()) - iy as isize)
+    }
+
+    ///loads or generates missing chunks within gen range of coords and evicts chunks that fell out of range
+    ///modified chunks are written to the save on eviction, unmodified ones just regenerate next time
+    ///with workers started, missing chunks are queued and picked up on a later call once loaded or generated
+    pub fn update_chunks(&mut self, coords: (isize, isize), chunk_dim: (usize, usize)) {
+        let ((ccx, ccy), _) = World::get_chunk_coords(coords, chunk_dim);                               //get chunk the coords are in
+        let (gen_range, evict_range) = (self.gen_range, self.gen_range + EVICT_MARGIN);
+        let in_range = |(cx, cy): (isize, isize)| (cx - ccx).abs() <= gen_range && (cy - ccy).abs() <= gen_range;
+        if let Some(pool) = &mut self.pool {
+            pool.set_focus((ccx, ccy), in_range);                                                       //stop waiting on chunks we walked away from
+            for chunk in pool.finished() {                                                              //take loaded and generated chunks
+                if in_range(chunk.chunk_coords) {self.data.entry(chunk.chunk_coords).or_insert(chunk.with_collision(&self.registry));}
             }
         }
+        let evicted: Vec<(isize, isize)> = self.data.keys().filter(|(cx, cy)| (cx - ccx).abs() > evict_range || (cy - ccy).abs() > evict_range).copied().collect();
+        let (mut stored, any_evicted) = (false, !evicted.is_empty());
+        for chunk_coords in evicted {                                                                   //drop chunks too far away
+            let chunk = self.data.remove(&chunk_coords).unwrap();
+            if let (true, Some(save)) = (chunk.modified, &mut self.save) {                              //keep edits to them
+                if let Err(e) = save.store_chunk(chunk, chunk_dim) {eprintln!("couldn't save chunk {:?}: {}", chunk_coords, e)}
+                stored = true;
+            }
+        }
+        for cy in ccy-gen_range..ccy+gen_range+1 {                                                      //for chunk row in gen range
+            for cx in ccx-gen_range..ccx+gen_range+1 {                                                  //for chunk in gen range of row
+                if self.data.contains_key(&(cx, cy)) || self.is_pending((cx, cy)) {continue}           //skip if loaded or on its way
+                if let Some(pool) = &mut self.pool {pool.request((cx, cy)); continue}                   //workers load or gen it
+                let saved = match &self.save {
+                    Some(save) => save.load_chunk((cx, cy), chunk_dim).unwrap_or_else(|e| {eprintln!("couldn't load chunk {:?}: {}", (cx, cy), e); None}),
+                    None => None,
+                };
+                let chunk = saved.unwrap_or_else(|| self.generator.gen_chunk((cx, cy), chunk_dim));    //gen it if it was never saved
+                self.data.insert((cx, cy), chunk.with_collision(&self.registry));
+            }
+        }
+        if let (true, Some(save)) = (stored, &mut self.save) {                                          //write evicted chunks, loading alone changes nothing on disk
+            if let Err(e) = save.flush(self.seed, &self.generator_kind, chunk_dim) {eprintln!("couldn't write save: {}", e)}
+        }
+        if let (true, Some(save)) = (any_evicted, &mut self.save) {                                     //forget regions we walked away from so memory stays flat
+            let pending = self.pool.iter().flat_map(|p| p.pending());
+            let needed: HashSet<(isize, isize)> = self.data.keys().chain(pending).map(|c| SaveDir::get_region_coords(*c)).collect();
+            save.drop_regions(|region| needed.contains(&region));
+        }
+    }
 
-        Self {
-            data,
-            _generator: generator,
-            seed: *seed,
-            _gen_range: gen_range,
+    ///writes every modified resident chunk to the save. does nothing if the world has no save
+    pub fn save(&mut self, chunk_dim: (usize, usize)) -> Result<(), SaveError> {
+        let save = match &mut self.save {Some(s) => s, None => return Ok(())};
+        for chunk in self.data.values_mut().filter(|c| c.modified) {
+            save.store_chunk(chunk.clone(), chunk_dim)?;
+            chunk.modified = false;
         }
+        save.flush(self.seed, &self.generator_kind, chunk_dim)
     }
 
-    ///calculates local coordinates in world vec from your global position
-    ///returns negative if above/left of rendered area
-    pub fn get_local_coords(&self, coords: (isize, isize), chunk_dim: (usize, usize)) -> (isize, isize) {
-        let (wx, wy) = self.data[0][0].chunk_coords;    //gets coords of first chunk in rendered vec
-        let lx = coords.0 - (wx * chunk_dim.0 as isize);//calculates local x coord based off world coords of first chunk
-        let ly = (wy * chunk_dim.1 as isize) - coords.1;//calculates local y coord based off world coords of first chunk
-        (lx, ly)
+    ///fnv hash of every resident chunks particles and state, in chunk order so two runs that ended up the same match
+    pub fn data_hash(&self) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |bytes: &[u8]| bytes.iter().for_each(|b| hash = (hash ^ *b as u64).wrapping_mul(0x0100_0000_01b3));
+        let mut coords: Vec<&(isize, isize)> = self.data.keys().collect();
+        coords.sort();
+        for c in coords {
+            let chunk = &self.data[c];
+            feed(&(c.0 as i64).to_le_bytes());
+            feed(&(c.1 as i64).to_le_bytes());
+            chunk.data.iter().for_each(|p| feed(&p.0.to_le_bytes()));
+            if let Some(state) = &chunk.state {
+                state.iter().for_each(|s| {feed(&s.temperature.to_le_bytes()); feed(&s.lifetime.to_le_bytes())});
+            }
+        }
+        hash
     }
 
-    pub fn get_local_pair(coord: isize, chunk: usize) -> (usize, usize) {
-        (coord as usize/chunk, coord as usize%chunk)
+    ///sets the particle at world coords, dropping its cell state, and wakes the chunks around it
+    ///does nothing if the chunk isnt loaded
+    pub fn set_particle(&mut self, coords: (isize, isize), particle: Particle, chunk_dim: (usize, usize)) {
+        let (chunk, (ix, iy)) = World::get_chunk_coords(coords, chunk_dim);
+        let i = iy*chunk_dim.0+ix;
+        match self.data.get_mut(&chunk) {
+            Some(c) => {
+                c.data[i] = particle;
+                c.set_collision(i, self.registry.get(particle.material()).collision);
+                c.replace_state(i, CellState::default());
+                c.modified = true;
+            },
+            None => return,
+        }
+        self.wake(coords, chunk_dim);
     }
 
     ///gets all visible pixels on screen relative camera position in world
     #[inline]
     pub fn get_screen(&self, screen: &mut Vec<u8>, camera_coords: (isize, isize), screen_dim: (usize, usize), chunk_dim: (usize, usize)) {
-        let camera = self.get_local_coords(camera_coords, chunk_dim);                       //gets loaded coords of camera in loaded chunks
-        (camera.1..camera.1 + screen_dim.1 as isize).enumerate().for_each(|(py,y)| {        //for screen pixel index and particle in range of camera loaded y
-            let (cy, ly) = World::get_local_pair(y, chunk_dim.1);                           //calculate chunk y and inner y from loaded y
-            if let Some(c_row) = self.data.get(cy) {                                        //if chunk row at loaded chunk y exists
-                (camera.0..camera.0 + screen_dim.0 as isize).enumerate().for_each(|(px,x)| {//for screen pixel index and particle in range of camera loaded x
-                    let (cx,lx) = World::get_local_pair(x, chunk_dim.0);                    //get loaded chunk x and inner x from loaded x
-                    let pos = (py*screen_dim.0+px)*4;
-                    if let Some(c) = c_row.get(cx) {screen[pos..pos+4].copy_from_slice(&c.data[ly*chunk_dim.0+lx].rgba[..])}  //if chunk in row then copy color of target particle in chunk
-                    else {screen[pos..pos+4].copy_from_slice(&[0;4][..])}                                           //if target chunk doesn't exist color black
-                })
-            } else {screen[(py*screen_dim.0)*4..(py*screen_dim.0+screen_dim.0)*4].chunks_exact_mut(4).for_each(|px| px.copy_from_slice(&[0;4][..]))}                       //if target chunk row doesn't exist color row black
+        let palette = self.registry.palette();                                                                  //particles index straight into the palette
+        screen.chunks_exact_mut(screen_dim.0*4).take(screen_dim.1).enumerate().for_each(|(py, row)| {        //for screen row
+            let wy = camera_coords.1 - py as isize;                                                             //world y of row
+            let mut px = 0;
+            while px < screen_dim.0 {                                                                           //walk row one chunk span at a time
+                let (chunk, (ix, iy)) = World::get_chunk_coords((camera_coords.0 + px as isize, wy), chunk_dim);
+                let span = (chunk_dim.0 - ix).min(screen_dim.0 - px);                                           //pixels left in chunk row or screen row
+                let out = &mut row[px*4..(px+span)*4];
+                if let Some(c) = self.data.get(&chunk) {                                                        //if chunk is loaded copy colors of particles in span
+                    let start = iy*chunk_dim.0+ix;
+                    out.chunks_exact_mut(4).zip(&c.data[start..start+span]).for_each(|(p, particle)| p.copy_from_slice(&palette[particle.0 as usize][..]));
+                } else if self.is_pending(chunk) {                                                              //if chunk is generating draw placeholder stripes
+                    let wx = camera_coords.0 + px as isize;
+                    out.chunks_exact_mut(4).enumerate().for_each(|(i, p)| p.copy_from_slice(&PLACEHOLDER[((wx + i as isize + wy).div_euclid(16) & 1) as usize]));
+                } else {out.iter_mut().for_each(|b| *b = 0)}                                                    //if chunk isnt loaded color black
+                px += span;
+            }
         });
+        for d in &self.debris {                                                                                 //draw flying debris over the grid
+            let (px, py) = (d.coords.0.round() as isize - camera_coords.0, camera_coords.1 - d.coords.1.round() as isize);
+            if px < 0 || py < 0 || px >= screen_dim.0 as isize || py >= screen_dim.1 as isize {continue}
+            let i = (py as usize*screen_dim.0 + px as usize)*4;
+            screen[i..i+4].copy_from_slice(&palette[d.particle.0 as usize][..]);
+        }
+    }
+
+    ///gets particle at world coords if its chunk is loaded
+    pub fn get_particle(&self, coords: (isize, isize), chunk_dim: (usize, usize)) -> Option<Particle> {
+        let (chunk, (ix, iy)) = World::get_chunk_coords(coords, chunk_dim);
+        self.data.get(&chunk).map(|c| c.data[iy*chunk_dim.0+ix])
     }
 
-    pub fn check_collision(&self, hitbox: Vec<(isize, isize)>, chunk_dim: (usize, usize)) -> bool {
-        for (wx,wy) in hitbox {                                     //for coord pair in hitbox vec
-            let (lx,ly) = self.get_local_coords((wx,wy), chunk_dim);//get local coords
-            let (lcx, inx) = World::get_local_pair(lx, chunk_dim.0);//get loaded x chunk and internal x
-            let (lcy, iny) = World::get_local_pair(ly, chunk_dim.1);//get loaded y chunk and internal y
-            if let Some(c_row) = self.data.get(lcy) {               //if row valid
-                if let Some(c) = c_row.get(lcx) {                   //and column valid
-                    if c.data[iny*chunk_dim.0+inx].collision {                 //if x,y has collision
-                       return true                                  //return true
+    ///swaps the particles and their cell state at two world coords and wakes the chunks around them
+    ///only edits mark chunks modified, so generated particles settling dont get saved. across chunks both are marked if either was
+    ///does nothing if either chunk isnt loaded
+    pub fn swap_particles(&mut self, a: (isize, isize), b: (isize, isize), chunk_dim: (usize, usize)) {
+        let (a_chunk, (ax, ay)) = World::get_chunk_coords(a, chunk_dim);
+        let (b_chunk, (bx, by)) = World::get_chunk_coords(b, chunk_dim);
+        let (ai, bi) = (ay*chunk_dim.0+ax, by*chunk_dim.0+bx);
+        if a_chunk == b_chunk {                                                         //if both in same chunk swap in place
+            match self.data.get_mut(&a_chunk) {
+                Some(c) => {
+                    c.data.swap(ai, bi);
+                    let (ca, cb) = (c.collides(ai), c.collides(bi));
+                    c.set_collision(ai, cb);
+                    c.set_collision(bi, ca);
+                    if let Some(state) = &mut c.state {state.swap(ai, bi)}
+                },
+                None => return,
+            }
+        } else {                                                                        //else copy across chunks
+            let (pa, sa, a_modified) = match self.data.get(&a_chunk) {Some(c) => (c.data[ai], c.get_state(ai), c.modified), None => return};
+            let (pb, sb, modified) = match self.data.get_mut(&b_chunk) {
+                Some(c) => {
+                    c.modified |= a_modified;                                   //edits carry over the border
+                    c.set_collision(bi, self.registry.get(pa.material()).collision);
+                    (std::mem::replace(&mut c.data[bi], pa), c.replace_state(bi, sa), c.modified)
+                },
+                None => return,
+            };
+            if let Some(c) = self.data.get_mut(&a_chunk) {
+                c.data[ai] = pb;
+                c.set_collision(ai, self.registry.get(pb.material()).collision);
+                c.replace_state(ai, sb);
+                c.modified = modified;
+            }
+        }
+        self.wake(a, chunk_dim);
+        self.wake(b, chunk_dim);
+    }
+
+    ///marks the chunk containing coords as active, plus its neighbors if coords sit on the chunk edge
+    pub fn wake(&mut self, coords: (isize, isize), chunk_dim: (usize, usize)) {
+        let ((cx, cy), (ix, iy)) = World::get_chunk_coords(coords, chunk_dim);
+        let dx = if ix == 0 {-1} else if ix == chunk_dim.0-1 {1} else {0};             //neighbor column if on left/right edge
+        let dy = if iy == 0 {1} else if iy == chunk_dim.1-1 {-1} else {0};              //neighbor row if on top/bottom edge
+        for chunk in &[(cx, cy), (cx+dx, cy), (cx, cy+dy), (cx+dx, cy+dy)] {
+            if let Some(c) = self.data.get_mut(chunk) {c.active = true}
+        }
+    }
+
+    ///returns what fraction of the hitbox is in liquid and the densest liquid touching it
+    pub fn submersion(&self, hitbox: &[(isize, isize)], chunk_dim: (usize, usize)) -> (f32, Option<&Material>) {
+        let mut wet = 0;
+        let mut liquid: Option<&Material> = None;
+        for coords in hitbox {
+            if let Some(p) = self.get_particle(*coords, chunk_dim) {
+                let material = self.registry.get(p.material());
+                if material.is_liquid() {
+                    wet += 1;
+                    match liquid {
+                        Some(l) if l.density >= material.density => {},
+                        _ => liquid = Some(material),
                     }
                 }
             }
         }
-        false                                                       //if none had collision return false
+        if hitbox.is_empty() {(0.0, None)}
+        else {(wet as f32 / hitbox.len() as f32, liquid)}
     }
 }
 
@@ -85,70 +331,73 @@ impl World {
 #[derive(Clone)]
 pub struct Chunk {                      //world chunk object
     pub chunk_coords: (isize,isize),    //chunk coordinates
-    pub data: Vec<Particle>,       //chunk Particle data
+    pub data: Vec<Particle>,            //chunk Particle data
+    pub state: Option<Vec<CellState>>,  //extra per particle state, only allocated once a particle in the chunk needs it
+    pub collision: Vec<u64>,            //bit per particle set if it collides, filled in when the chunk joins the world
+    pub active: bool,                   //if chunk needs simulating next tick
+    pub modified: bool,                 //if chunk differs from what its seed generates and needs saving
 }
 
 impl Chunk {
-    ///generates chunk using perlin noise
-    fn gen_perlin(chunk_coords: (isize, isize), generator: Perlin, chunk_dim: (usize, usize)) -> Self {
-        let mut data = vec!(vec!(Particle::new([0;4], false); chunk_dim.0); chunk_dim.1);               //creates empty vec for particles
-        for y in 0..data.len() {                                                                        //for row in len of chunk
-
-            let gen_depth = {                                                                           //generates number based off depth that slowly climb's from -1 up to 0.1
-                let mut depth = (((chunk_coords.1*chunk_dim.1 as isize) as f64-y as f64)/1000.0)*-1.0;  //  this makes it so caves don't generate at the surface
-                depth = depth - 1.0;
-                if depth > 0.1 {
-                    0.1
-                } else {depth}
-            };
-
-            for x in 0..data[y].len() {                                                                 //for particle in row
-                let perlx = ((chunk_coords.0 * chunk_dim.0 as isize) as f64 + x as f64)/500.0;          //set coords for perlin noise
-                let perly = ((chunk_coords.1 * chunk_dim.1 as isize) as f64 - y as f64)/500.0;
-                let noise = generator.get([perlx,perly]);                                               //get noise for chunk
-
-                let mut ground = 0;                                                                     //sets ground level default to 0 (always above)
-                if chunk_coords.1 == 0 {                                                                //if coord is at 0 ground level
-                    ground = (255.0 * ((noise+1.0)/2.0)) as usize;                                      //convert noise to 0-255 to resemble ground level
-                } else if chunk_coords.1 > 0 {ground = 256}                                             //else if chunk below ground set ground level to 256 (always below)
-
-                if y >= ground {                                                                        //if y below ground level
-                    let particle = {                                                                    //create particle:
-                        if noise > gen_depth {Particle::new([124, 94, 66, 255], true)}                  //if noise great enough to gen caves at current depth level return cave particle
-                        else {Particle::new([135, 206, 235, 0], false)}                                 //else return sky particle
-                    };                                                                                  //map noise to shades of grey
-                    data[y][x] = particle;                                                              //copy color to particle in chunk x,y
-                } else {data[y][x] = Particle::new([135, 206, 235, 0], false)}
-            }
+    ///gets state of particle at inner index, default if chunk has none
+    pub fn get_state(&self, i: usize) -> CellState {
+        match &self.state {
+            Some(state) => state[i],
+            None => CellState::default(),
         }
-        //BLACK BOX
-        //for y in 0..chunk_height/25 {                                                                 //creates little black box to show upper left of chunk
-        //    for x in 0..chunk_width/25 {
-        //        data[y][x].rgba = [0;4];
-        //    }
-        //}
-        Self {                                                                                          //return chunk
+    }
+
+    ///gets state of particle at inner index for writing, allocating chunk state if needed
+    pub fn state_mut(&mut self, i: usize) -> &mut CellState {
+        let len = self.data.len();
+        &mut self.state.get_or_insert_with(|| vec![CellState::default(); len])[i]
+    }
+
+    ///sets state of particle at inner index and returns the old one. doesnt allocate just to store a default
+    pub fn replace_state(&mut self, i: usize, new: CellState) -> CellState {
+        if self.state.is_none() && new == CellState::default() {return new}
+        std::mem::replace(self.state_mut(i), new)
+    }
+
+    ///creates a freshly generated chunk from its particles
+    pub fn new(chunk_coords: (isize, isize), data: Vec<Particle>) -> Self {
+        Self {
             chunk_coords,
-            data: data.into_iter().flatten().collect(),                                                 //BAD BUT IM LAZY RN WILL FIX DURING WORLD GEN IMPL
+            data,
+            state: None,
+            collision: Vec::new(),
+            active: true,                       //simulate once so loose particles settle
+            modified: false,
         }
     }
 }
 
 
 
-///contains all particle data
-#[derive(Clone)]
-pub struct Particle {   //Particle particle data
-    pub rgba: [u8;4],   //rgba color code
-    pub collision: bool,//if it has collision
-}
+///contains all particle data packed into 16 bits
+///material id in the high bits and color shade in the low SHADE_BITS, so the whole value indexes the palette
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Particle(pub u16);
 
 impl Particle {
-    ///creates a colored particle
-    fn new(rgba: [u8;4], collision: bool) -> Self {  //generate new particle
-        Self {
-            rgba,
-            collision,
-        }
+    ///creates a particle with its shade picked from where it is
+    pub fn new(material: MaterialId, coords: (isize, isize)) -> Self {  //generate new particle
+        Particle(material << SHADE_BITS | shade_at(coords) as u16)
+    }
+
+    ///what the particle is made of
+    #[inline]
+    pub fn material(self) -> MaterialId {
+        self.0 >> SHADE_BITS
     }
-}
\ No newline at end of file
+}
+
+
+
+///per particle state that only some particles need
+///only stored for now, kept with the particle as it moves and saved with its chunk. nothing simulates either field yet
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct CellState {
+    pub temperature: i16,
+    pub lifetime: u16,
+}
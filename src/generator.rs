@@ -0,0 +1,434 @@
+use noise::{NoiseFn, Perlin, Seedable};
+use std::fmt;
+use std::str::FromStr;
+use crate::gen::*;
+use crate::material::*;
+use crate::prefab::{cell_hash, Placement, Prefab};
+
+const SURFACE_DEPTH: usize = 3; //how many px of loose dirt sit on top of the ground
+const POOL_THRESHOLD: f64 = 0.35;//how much pool noise a cave needs to be flooded
+const LAVA_DEPTH: isize = -2000;//world y below which flooded caves fill with lava
+const FLAT_GROUND: isize = -128;//world y of the flat world surface
+const SURFACE_LEVEL: f64 = -128.0;//world y the perlin surface rises and falls around
+const TERRAIN_SCALE: f64 = 1000.0;//px per unit of height noise
+const TERRAIN_OCTAVES: usize = 5;
+const BIOME_SCALE: f64 = 4000.0;//px per unit of biome noise so biomes span several screens
+const SPAWN_HEIGHT: isize = 64; //how far above the surface players spawn
+const HEIGHTMAP_FLOOR: isize = -512;//world y of the surface under black heightmap columns
+const HEIGHTMAP_RANGE: isize = 512; //how many px white columns rise above black ones
+const TOPSOIL_DEPTH: f64 = 48.0;//average px of topsoil under the biome surface before stone
+const DEEP_STONE_Y: f64 = -1500.0;//average world y stone turns to deep stone
+const BEDROCK_Y: f64 = -6000.0; //average world y of the bedrock floor
+const STRATA_SCALE: f64 = 300.0;//px per unit of strata boundary noise
+const PREFAB_FILE: &str = "prefabs.toml";
+
+
+
+///anything that can fill in a chunk of the world from its coords
+///has to give the same chunk every time so unmodified chunks dont need saving
+///shared with the chunk worker threads
+pub trait WorldGenerator: Send + Sync {
+    fn gen_chunk(&self, chunk_coords: (isize, isize), chunk_dim: (usize, usize)) -> Chunk;
+
+    ///name of the biome at world x, if the generator has biomes
+    fn biome_at(&self, _wx: isize) -> Option<&'static str> {
+        None
+    }
+
+    ///where players first appear, clear of the ground
+    fn spawn_point(&self) -> (isize, isize) {
+        (0, 0)
+    }
+}
+
+///which generator a world uses, parsed from the command line and kept in saves
+#[derive(Clone, PartialEq, Debug)]
+pub enum GeneratorKind {
+    Perlin,
+    Flat,
+    Empty,
+    Heightmap(String),  //path to png
+}
+
+impl GeneratorKind {
+    ///creates the generator for a world
+    pub fn build(&self, seed: u32, registry: &Registry) -> Result<Box<dyn WorldGenerator>, String> {
+        Ok(match self {
+            GeneratorKind::Perlin => Box::new(PerlinGenerator::new(seed, registry, Prefab::load_all(PREFAB_FILE, registry)?)?),
+            GeneratorKind::Flat => Box::new(FlatGenerator::new(registry)?),
+            GeneratorKind::Empty => Box::new(EmptyGenerator),
+            GeneratorKind::Heightmap(path) => Box::new(HeightmapGenerator::load(path, registry)?),
+        })
+    }
+}
+
+impl FromStr for GeneratorKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "perlin" => Ok(GeneratorKind::Perlin),
+            "flat" => Ok(GeneratorKind::Flat),
+            "empty" => Ok(GeneratorKind::Empty),
+            _ if s.starts_with("heightmap:") => Ok(GeneratorKind::Heightmap(s["heightmap:".len()..].to_string())),
+            _ => Err(format!("unknown generator {}, expected perlin, flat, empty or heightmap:<png>", s)),
+        }
+    }
+}
+
+impl fmt::Display for GeneratorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GeneratorKind::Perlin => write!(f, "perlin"),
+            GeneratorKind::Flat => write!(f, "flat"),
+            GeneratorKind::Empty => write!(f, "empty"),
+            GeneratorKind::Heightmap(path) => write!(f, "heightmap:{}", path),
+        }
+    }
+}
+
+
+
+///ids of the materials the generators place, looked up from the registry once
+#[derive(Clone, Copy)]
+struct GenMaterials {
+    ground: MaterialId,
+    dirt: MaterialId,
+    water: MaterialId,
+    lava: MaterialId,
+    oil: MaterialId,
+}
+
+impl GenMaterials {
+    fn new(registry: &Registry) -> Result<Self, String> {
+        let id = |name| registry.id(name).ok_or_else(|| format!("material registry is missing {}", name));
+        Ok(Self {
+            ground: id("ground")?,
+            dirt: id("dirt")?,
+            water: id("water")?,
+            lava: id("lava")?,
+            oil: id("oil")?,
+        })
+    }
+}
+
+
+
+///a kind of terrain the surface can be, picked by low frequency biome noise
+struct Biome {
+    name: &'static str,
+    center: f64,            //biome noise value this biome is centered on, neighbors blend between centers
+    surface: &'static str,  //material covering the ground
+    surface_depth: usize,   //how many px deep the surface material goes
+    amplitude: f64,         //how many px the surface rises and falls
+    cave_density: f64,      //added to the cave threshold, higher carves out more caves
+}
+
+const BIOMES: [Biome; 4] = [    //sorted by center
+    Biome {name: "desert",    center: -0.35, surface: "sand",   surface_depth: 24, amplitude: 40.0,  cave_density: -0.05},
+    Biome {name: "forest",    center: -0.1,  surface: "dirt",   surface_depth: 8,  amplitude: 120.0, cave_density: 0.0},
+    Biome {name: "tundra",    center: 0.15,  surface: "snow",   surface_depth: 6,  amplitude: 80.0,  cave_density: -0.1},
+    Biome {name: "mountains", center: 0.4,   surface: "gravel", surface_depth: 3,  amplitude: 1400.0,cave_density: 0.1},
+];
+
+///a kind of ore vein and how deep it starts showing up
+struct Ore {
+    material: &'static str,
+    scale: f64,             //px per unit of vein noise, smaller is shorter thinner veins
+    threshold: f64,         //vein mask noise needed at full depth, higher is rarer
+    min_depth: isize,       //px under the surface veins start
+    full_depth: isize,      //px under the surface veins reach full size. rarer above it
+}
+
+const ORES: [Ore; 4] = [        //rarest first so it wins where veins overlap
+    Ore {material: "gems", scale: 30.0,  threshold: 0.5,  min_depth: 2500, full_depth: 5000},
+    Ore {material: "gold", scale: 50.0,  threshold: 0.4,  min_depth: 1000, full_depth: 3000},
+    Ore {material: "iron", scale: 80.0,  threshold: 0.3,  min_depth: 200,  full_depth: 1200},
+    Ore {material: "coal", scale: 100.0, threshold: 0.2,  min_depth: 30,   full_depth: 300},
+];
+const ORE_RARITY: f64 = 0.3;    //how much higher the mask threshold is where veins first start
+const VEIN_WIDTH: f64 = 0.03;   //how close to a zero crossing of vein noise counts as inside the vein
+
+///sums octaves of noise, each double the frequency and half the strength of the last. returns roughly -1 to 1
+fn fbm(noise: &Perlin, point: [f64;2], octaves: usize) -> f64 {
+    let (mut sum, mut strength, mut freq, mut total) = (0.0, 1.0, 1.0, 0.0);
+    for _ in 0..octaves {
+        sum += noise.get([point[0]*freq, point[1]*freq]) * strength;
+        total += strength;
+        strength *= 0.5;
+        freq *= 2.0;
+    }
+    sum / total
+}
+
+
+
+///everything about a column of terrain that doesnt change with depth
+struct Column {
+    surface: isize,                     //world y of the ground surface
+    biome: usize,
+    cave_density: f64,
+    topsoil: isize,                     //px under the surface topsoil turns to stone
+    deep_stone: isize,                  //world y stone turns to deep stone
+    bedrock: isize,                     //world y bedrock starts
+}
+
+///fractal perlin terrain split into biomes and rock strata, with caves, ore veins and flooded pockets
+pub struct PerlinGenerator {
+    seed: u32,
+    noise: Perlin,                      //caves and pools
+    height_noise: Perlin,               //surface line
+    biome_noise: Perlin,
+    strata_noise: Perlin,               //wobbles layer boundaries
+    ore_noise: Perlin,                  //3d, one slice per ore
+    materials: GenMaterials,
+    surfaces: [MaterialId; 4],          //surface material of each biome
+    strata: [MaterialId; 3],            //stone, deep stone, bedrock
+    ores: [MaterialId; 4],              //same order as ORES
+    prefabs: Vec<Prefab>,               //structures stamped over the terrain
+}
+
+impl PerlinGenerator {
+    pub fn new(seed: u32, registry: &Registry, prefabs: Vec<Prefab>) -> Result<Self, String> {
+        let id = |name| registry.id(name).ok_or_else(|| format!("material registry is missing {}", name));
+        Ok(Self {
+            seed,
+            noise: Perlin::new().set_seed(seed),
+            height_noise: Perlin::new().set_seed(seed.wrapping_add(1)),
+            biome_noise: Perlin::new().set_seed(seed.wrapping_add(2)),
+            strata_noise: Perlin::new().set_seed(seed.wrapping_add(3)),
+            ore_noise: Perlin::new().set_seed(seed.wrapping_add(4)),
+            materials: GenMaterials::new(registry)?,
+            surfaces: [id(BIOMES[0].surface)?, id(BIOMES[1].surface)?, id(BIOMES[2].surface)?, id(BIOMES[3].surface)?],
+            strata: [id("stone")?, id("deep_stone")?, id("bedrock")?],
+            ores: [id(ORES[0].material)?, id(ORES[1].material)?, id(ORES[2].material)?, id(ORES[3].material)?],
+            prefabs,
+        })
+    }
+
+    ///if world coords under a column are carved out as cave
+    fn is_cave(&self, column: &Column, wx: isize, wy: isize) -> bool {
+        let depth = column.surface - wy;                                                                //how far under the surface
+        if depth < BIOMES[column.biome].surface_depth as isize || wy <= column.bedrock {return false}   //surface layer and bedrock are never carved
+        let gen_depth = (depth as f64/1000.0 - 1.0).min(0.1) + column.cave_density;                     //climbs from -1 up to 0.1 so caves don't generate at the surface
+        self.noise.get([wx as f64/500.0, wy as f64/500.0]) <= gen_depth                                 //if noise low enough to gen caves at current depth level
+    }
+
+    ///returns where the bottom middle of a prefab goes in a placement cell, if it gets placed there
+    ///pure function of seed and cell so chunks on either side of a structure agree no matter which generates first
+    fn get_anchor(&self, index: usize, prefab: &Prefab, cell: (isize, isize)) -> Option<(isize, isize)> {
+        let (roll, offset) = cell_hash(self.seed, index, cell);
+        if roll >= prefab.chance {return None}
+        let s = prefab.spacing;
+        let wx = cell.0*s + (offset % s as u64) as isize;
+        let column = self.get_column(wx);
+        match prefab.placement {
+            Placement::Surface => {
+                if !prefab.biomes.is_empty() && !prefab.biomes.iter().any(|b| b == BIOMES[column.biome].name) {return None}
+                Some((wx, column.surface + 1 - prefab.sink))                                            //sit on top of the ground
+            },
+            Placement::Cave => {
+                let wy = cell.1*s + ((offset >> 24) % s as u64) as isize;
+                if column.surface - wy < prefab.min_depth || !self.is_cave(&column, wx, wy) {return None}   //only float in open caves deep enough
+                Some((wx, wy))
+            },
+        }
+    }
+
+    ///stamps every structure that overlaps the chunk into it
+    fn place_structures(&self, chunk: &mut Chunk, chunk_dim: (usize, usize)) {
+        let (left, top) = (chunk.chunk_coords.0*chunk_dim.0 as isize, chunk.chunk_coords.1*chunk_dim.1 as isize);
+        let (right, bottom) = (left + chunk_dim.0 as isize, top - chunk_dim.1 as isize);
+        let bedrock = self.strata[2];
+        for (i, prefab) in self.prefabs.iter().enumerate() {
+            let (s, w, h) = (prefab.spacing, prefab.width as isize, prefab.height as isize);
+            let cells_x = (left - w).div_euclid(s)..(right + w).div_euclid(s) + 1;                      //cells whose structures could reach the chunk
+            let cells_y = match prefab.placement {
+                Placement::Surface => 0..1,                                                             //surface cells are columns
+                Placement::Cave => (bottom - h).div_euclid(s)..(top + h).div_euclid(s) + 1,
+            };
+            for cy in cells_y {
+                for cx in cells_x.clone() {
+                    if let Some(anchor) = self.get_anchor(i, prefab, (cx, cy)) {
+                        prefab.stamp(chunk, prefab.origin(anchor), chunk_dim, |p| p.material() == bedrock);   //structures cant cut through bedrock
+                    }
+                }
+            }
+        }
+    }
+
+    ///works out the surface, biome and layer boundaries of the column at world x
+    fn get_column(&self, wx: isize) -> Column {
+        let (biome, amplitude, cave_density) = self.get_biome(wx);
+        let wobble = |layer: f64| self.strata_noise.get([wx as f64/STRATA_SCALE, layer]);              //separate slice of noise per boundary
+        Column {
+            surface: self.get_surface(wx, amplitude),
+            biome,
+            cave_density,
+            topsoil: (TOPSOIL_DEPTH + wobble(0.5) * 24.0) as isize,
+            deep_stone: (DEEP_STONE_Y + wobble(1.5) * 120.0) as isize,
+            bedrock: (BEDROCK_Y + wobble(2.5) * 40.0) as isize,
+        }
+    }
+
+    ///returns the rock at a solid cell under the surface layer, with any ore vein running through it
+    fn get_rock(&self, column: &Column, wx: isize, wy: isize, depth: isize) -> MaterialId {
+        if depth < column.topsoil {return self.materials.ground}                                        //topsoil has no ore
+        let rock = if wy > column.deep_stone {self.strata[0]} else {self.strata[1]};
+        for (i, ore) in ORES.iter().enumerate() {
+            if depth < ore.min_depth {continue}
+            let t = ((depth - ore.min_depth) as f64 / (ore.full_depth - ore.min_depth) as f64).min(1.0);   //0 where veins start, 1 at full size
+            let (x, y, z) = (wx as f64/ore.scale, wy as f64/ore.scale, i as f64*2.0 + 0.5);             //separate slices of noise per ore
+            if self.ore_noise.get([x, y, z]).abs() > VEIN_WIDTH {continue}                              //veins follow the winding zero lines of the noise
+            if self.ore_noise.get([x/4.0, y/4.0, z + 1.0]) > ore.threshold + ORE_RARITY * (1.0 - t) {return self.ores[i]}  //but only where the mask lets them through
+        }
+        rock
+    }
+
+    ///returns the index of the biome at world x and the surface amplitude and cave density there
+    ///amplitude and cave density are blended between neighboring biome centers so biome borders dont leave cliffs or seams
+    fn get_biome(&self, wx: isize) -> (usize, f64, f64) {
+        let b = self.biome_noise.get([wx as f64/BIOME_SCALE, 0.5]);
+        let biome = (0..BIOMES.len()).min_by(|&i, &j| (BIOMES[i].center - b).abs().partial_cmp(&(BIOMES[j].center - b).abs()).unwrap()).unwrap();
+        let (lower, upper, t) = match BIOMES.iter().position(|biome| biome.center > b) {                //biomes either side of b and how far between them
+            Some(0) => (0, 0, 0.0),
+            Some(i) => (i-1, i, (b - BIOMES[i-1].center) / (BIOMES[i].center - BIOMES[i-1].center)),
+            None => (BIOMES.len()-1, BIOMES.len()-1, 0.0),
+        };
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+        (biome, lerp(BIOMES[lower].amplitude, BIOMES[upper].amplitude), lerp(BIOMES[lower].cave_density, BIOMES[upper].cave_density))
+    }
+
+    ///returns world y of the ground surface at world x
+    fn get_surface(&self, wx: isize, amplitude: f64) -> isize {
+        (SURFACE_LEVEL + fbm(&self.height_noise, [wx as f64/TERRAIN_SCALE, 0.5], TERRAIN_OCTAVES) * amplitude) as isize
+    }
+
+    ///returns what fills a cave cell. most caves are empty but some pockets are flooded and left to drain into the rest
+    fn gen_pool(&self, perl: (f64, f64), wy: isize) -> MaterialId {
+        let pool = self.noise.get([perl.0*4.0, perl.1*4.0, 0.5]);                                     //separate higher frequency noise for pools
+        if pool < POOL_THRESHOLD {AIR}
+        else if wy < LAVA_DEPTH {self.materials.lava}                                                   //deep pools are lava
+        else if pool > POOL_THRESHOLD + 0.15 {self.materials.oil}                                       //strongest pockets are oil
+        else {self.materials.water}
+    }
+}
+
+impl WorldGenerator for PerlinGenerator {
+    ///generates chunk using fbm surface, noisy strata and perlin caves
+    fn gen_chunk(&self, chunk_coords: (isize, isize), chunk_dim: (usize, usize)) -> Chunk {
+        let columns: Vec<Column> = (0..chunk_dim.0).map(|x| self.get_column(chunk_coords.0 * chunk_dim.0 as isize + x as isize)).collect();
+
+        let mut data = vec!(Particle::new(AIR, (0,0)); chunk_dim.0*chunk_dim.1);                        //creates empty vec for particles
+        for y in 0..chunk_dim.1 {                                                                       //for row in len of chunk
+            let wy = chunk_coords.1 * chunk_dim.1 as isize - y as isize;                                //world y of row
+            for (x, column) in columns.iter().enumerate() {                                             //for particle in row
+                let wx = chunk_coords.0 * chunk_dim.0 as isize + x as isize;                            //world x of particle
+                let depth = column.surface - wy;                                                        //how far under the surface
+                let particle = if depth < 0 {AIR}                                                       //above ground
+                    else if depth < BIOMES[column.biome].surface_depth as isize {self.surfaces[column.biome]}   //biome surface layer
+                    else if wy <= column.bedrock {self.strata[2]}                                       //nothing carves bedrock
+                    else if self.is_cave(column, wx, wy) {self.gen_pool((wx as f64/500.0, wy as f64/500.0), wy)}  //caves are empty or flooded
+                    else {self.get_rock(column, wx, wy, depth)};
+                data[y*chunk_dim.0+x] = Particle::new(particle, (wx, wy));                              //copy particle to chunk x,y
+            }
+        }
+        let mut chunk = Chunk::new(chunk_coords, data);
+        self.place_structures(&mut chunk, chunk_dim);
+        chunk                                                                                           //return chunk
+    }
+
+    fn biome_at(&self, wx: isize) -> Option<&'static str> {
+        Some(BIOMES[self.get_biome(wx).0].name)
+    }
+
+    fn spawn_point(&self) -> (isize, isize) {
+        (0, self.get_surface(0, self.get_biome(0).1) + SPAWN_HEIGHT)
+    }
+}
+
+
+
+///level ground with no caves, for testing movement and physics
+pub struct FlatGenerator {
+    materials: GenMaterials,
+}
+
+impl FlatGenerator {
+    pub fn new(registry: &Registry) -> Result<Self, String> {
+        Ok(Self {materials: GenMaterials::new(registry)?})
+    }
+}
+
+impl WorldGenerator for FlatGenerator {
+    fn gen_chunk(&self, chunk_coords: (isize, isize), chunk_dim: (usize, usize)) -> Chunk {
+        let data = (0..chunk_dim.0*chunk_dim.1).map(|i| {
+            let (wx, wy) = World::get_world_coords(chunk_coords, i, chunk_dim);
+            let material = if wy > FLAT_GROUND {AIR}
+                           else if wy > FLAT_GROUND - SURFACE_DEPTH as isize {self.materials.dirt}
+                           else {self.materials.ground};
+            Particle::new(material, (wx, wy))
+        }).collect();
+        Chunk::new(chunk_coords, data)
+    }
+}
+
+
+
+///nothing but air, a sandbox to build in
+pub struct EmptyGenerator;
+
+impl WorldGenerator for EmptyGenerator {
+    fn gen_chunk(&self, chunk_coords: (isize, isize), chunk_dim: (usize, usize)) -> Chunk {
+        Chunk::new(chunk_coords, vec![Particle(AIR); chunk_dim.0*chunk_dim.1])
+    }
+}
+
+
+
+///terrain from a png read as a heightmap, each columns brightness is how high the ground is there
+///black is HEIGHTMAP_FLOOR and white HEIGHTMAP_RANGE px above it. rows are averaged so a 1px tall strip works
+///one image column per px of world, repeating sideways
+pub struct HeightmapGenerator {
+    surface: Vec<isize>,    //world y of the ground surface under each image column
+    materials: GenMaterials,
+}
+
+impl HeightmapGenerator {
+    pub fn load(path: &str, registry: &Registry) -> Result<Self, String> {
+        let img = image::open(path).map_err(|e| format!("couldn't load heightmap {}: {}", path, e))?.to_luma8();
+        let (width, height) = (img.width(), img.height());
+        if width == 0 || height == 0 {return Err(format!("heightmap {} is empty", path))}
+        let surface = (0..width).map(|x| {
+            let brightness = (0..height).map(|y| img.get_pixel(x, y).0[0] as isize).sum::<isize>() / height as isize;
+            HEIGHTMAP_FLOOR + brightness * HEIGHTMAP_RANGE / 255
+        }).collect();
+        Ok(Self {
+            surface,
+            materials: GenMaterials::new(registry)?,
+        })
+    }
+
+    ///returns world y of the ground surface at world x
+    fn get_surface(&self, wx: isize) -> isize {
+        self.surface[wx.rem_euclid(self.surface.len() as isize) as usize]
+    }
+}
+
+impl WorldGenerator for HeightmapGenerator {
+    fn gen_chunk(&self, chunk_coords: (isize, isize), chunk_dim: (usize, usize)) -> Chunk {
+        let data = (0..chunk_dim.0*chunk_dim.1).map(|i| {
+            let (wx, wy) = World::get_world_coords(chunk_coords, i, chunk_dim);
+            let depth = self.get_surface(wx) - wy;                                                      //how far under the surface
+            let material = if depth < 0 {AIR}
+                           else if depth < SURFACE_DEPTH as isize {self.materials.dirt}                 //loose dirt on top
+                           else {self.materials.ground};
+            Particle::new(material, (wx, wy))
+        }).collect();
+        Chunk::new(chunk_coords, data)
+    }
+
+    ///above the first open cell over the ground at x 0
+    fn spawn_point(&self) -> (isize, isize) {
+        (0, self.get_surface(0) + 1 + SPAWN_HEIGHT)
+    }
+}
@@ -0,0 +1,150 @@
+use crate::material::{MaterialId, Registry};
+use crate::tool::Tool;
+
+pub const HOTBAR_SLOTS: usize = 9;          //first row of the inventory, picked with number keys and scrolling
+pub const INVENTORY_SLOTS: usize = HOTBAR_SLOTS*4;
+const MATERIAL_STACK: usize = 10_000;       //particles in one slot, a full brush of dirt is a few hundred
+const STARTING_ITEMS: [(Item, usize); 5] = [
+    (Item::Tool(Tool::Shovel, Tool::Shovel.durability()), 1),
+    (Item::Tool(Tool::Pickaxe, Tool::Pickaxe.durability()), 1),
+    (Item::Tool(Tool::Drill, Tool::Drill.durability()), 1),
+    (Item::Bomb, 8),
+    (Item::Bed, 1),
+];
+
+///something the player can carry
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Item {
+    Material(MaterialId),               //loose particles dug out of the world, counted one per particle
+    Bomb,                               //blows up under the cursor
+    Bed,                                //placed as a block of bed to respawn at
+    Tool(Tool, u16),                    //digs while its in hand, with the ticks of digging it has left
+}
+
+impl Item {
+    ///most of the item one slot holds
+    pub fn max_stack(&self) -> usize {
+        match self {
+            Item::Material(_) => MATERIAL_STACK,
+            Item::Bomb => 16,
+            Item::Bed | Item::Tool(..) => 1,
+        }
+    }
+
+    pub fn name(&self, registry: &Registry) -> String {
+        match self {
+            Item::Material(id) => registry.get(*id).name.clone(),
+            Item::Bomb => "bomb".to_string(),
+            Item::Bed => "bed".to_string(),
+            Item::Tool(tool, _) => tool.name().to_string(),
+        }
+    }
+
+    ///color its slot is drawn with
+    pub fn color(&self, registry: &Registry) -> [u8; 4] {
+        match self {
+            Item::Material(id) => registry.get(*id).color,
+            Item::Bomb => [50,50,56,255],
+            Item::Bed => [178,34,52,255],
+            Item::Tool(Tool::Shovel, _) => [160,120,70,255],
+            Item::Tool(Tool::Pickaxe, _) => [140,140,150,255],
+            Item::Tool(Tool::Drill, _) => [230,160,40,255],
+        }
+    }
+}
+
+///some amount of one item in a slot
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Stack {
+    pub item: Item,
+    pub count: usize,
+}
+
+///slots the player carries things in, the first HOTBAR_SLOTS make up the hotbar
+pub struct Inventory {
+    pub slots: Vec<Option<Stack>>,
+    pub selected: usize,                //hotbar slot in hand
+}
+
+impl Inventory {
+    ///an inventory holding what a new player starts with
+    pub fn starting() -> Self {
+        let mut inventory = Self {slots: vec![None; INVENTORY_SLOTS], selected: 0};
+        for (item, count) in STARTING_ITEMS.iter() {inventory.add(*item, *count);}
+        inventory
+    }
+
+    ///adds items, topping up stacks of the same item before filling empty slots. returns how many didnt fit
+    pub fn add(&mut self, item: Item, count: usize) -> usize {
+        let mut left = count;
+        for slot in self.slots.iter_mut().filter_map(|s| s.as_mut()).filter(|s| s.item == item) {
+            let moved = left.min(item.max_stack() - slot.count.min(item.max_stack()));
+            slot.count += moved;
+            left -= moved;
+        }
+        for slot in self.slots.iter_mut().filter(|s| s.is_none()) {
+            if left == 0 {break}
+            let moved = left.min(item.max_stack());
+            *slot = Some(Stack {item, count: moved});
+            left -= moved;
+        }
+        left
+    }
+
+    ///takes up to count out of a slot, emptying it if it runs out. returns how many were taken
+    pub fn take(&mut self, slot: usize, count: usize) -> usize {
+        let stack = match &mut self.slots[slot] {Some(s) => s, None => return 0};
+        let taken = count.min(stack.count);
+        stack.count -= taken;
+        if stack.count == 0 {self.slots[slot] = None}
+        taken
+    }
+
+    ///takes up to count of an item from wherever it is. returns how many were taken
+    pub fn remove(&mut self, item: Item, count: usize) -> usize {
+        let mut taken = 0;
+        for slot in (0..self.slots.len()).rev() {                                       //emptying from the back leaves the hotbar alone longest
+            if taken == count {break}
+            if matches!(self.slots[slot], Some(s) if s.item == item) {taken += self.take(slot, count - taken)}
+        }
+        taken
+    }
+
+    ///how much of an item is carried in total
+    pub fn count(&self, item: Item) -> usize {
+        self.slots.iter().flatten().filter(|s| s.item == item).map(|s| s.count).sum()
+    }
+
+    ///the stack in hand, if the selected hotbar slot isnt empty
+    pub fn held(&self) -> Option<Stack> {
+        self.slots[self.selected]
+    }
+
+    pub fn select(&mut self, slot: usize) {
+        if slot < HOTBAR_SLOTS {self.selected = slot}
+    }
+
+    ///moves the selection along the hotbar, wrapping around the ends
+    pub fn scroll(&mut self, step: isize) {
+        self.selected = (self.selected as isize + step).rem_euclid(HOTBAR_SLOTS as isize) as usize;
+    }
+
+    ///wears down the tool in a slot by a tick of digging, breaking it once its used up. anything else is left alone
+    pub fn wear(&mut self, slot: usize) {
+        if let Some(Stack {item: Item::Tool(tool, left), ..}) = self.slots[slot] {
+            self.slots[slot] = if left > 1 {Some(Stack {item: Item::Tool(tool, left - 1), count: 1})} else {None};
+        }
+    }
+
+    ///swaps two slots, merging them instead if they hold the same item
+    pub fn swap(&mut self, a: usize, b: usize) {
+        match (self.slots[a], self.slots[b]) {
+            (Some(from), Some(to)) if a != b && from.item == to.item => {
+                let moved = from.count.min(to.item.max_stack() - to.count.min(to.item.max_stack()));
+                self.slots[b] = Some(Stack {count: to.count + moved, ..to});
+                self.take(a, moved);
+            },
+            _ => self.slots.swap(a, b),
+        }
+    }
+}
@@ -3,23 +3,51 @@ extern crate test;
 
 mod player;
 mod gen;
+mod sim;
+mod material;
+mod save;
+mod generator;
+mod prefab;
+mod pool;
+mod edit;
+mod explosion;
+mod collision;
+mod timestep;
+mod script;
+mod bindings;
+mod config;
+mod state;
+mod inventory;
+mod tool;
 use engine::{drawing, game, sprite};
 
-const SCREEN_DIM: (usize, usize) = (1920,1080);//960, 528;
 //const ASPECT_RATIO: f32 = 9.0/16.0;
 //const SCREEN_WIDTH: usize = (SCREEN_HEIGHT as f32 / ASPECT_RATIO)as usize;
-const TARGET_FPS: u64 = 60_000; //target should be 60 but i set to 60_000 when optimizing to see uncapped fps
+//resolution, fullscreen, fps cap, chunk size, view distance, seed and debug are in config.toml, see config::Config
+const TICK_RATE: u32 = 60;              //game updates per second no matter the fps
+const MAX_CATCH_UP: usize = 5;          //most ticks run in one frame before the game slows down instead
 
 const GAME_TITLE: &str = "Untitled Game v0.002";
-const ENABLE_DEBUG: bool = true;        //if debug can be toggled
 
-const CHUNK_DIM: (usize, usize) = (256,256);
-const GEN_RANGE: isize = 25;            //how far out to gen chunks
-const SET_SEED: bool = true;            //if seed should be set
+const WORKER_THREADS: usize = 4;        //threads generating chunks in the background
+const MATERIAL_FILE: &str = "materials.toml";
+const CONTROLS_FILE: &str = "controls.toml";
+const DEFAULT_GENERATOR: &str = "perlin";   //perlin, flat, empty or heightmap:<png>. overridden by --generator
+const BRUSH_RADIUS: isize = 6;          //starting radius of the dig/place brush
+const MAX_BRUSH_RADIUS: isize = 48;
+const EXPLOSION_RADIUS: isize = 32;     //size of middle click explosions
+const EXPLOSION_POWER: f32 = 12.0;      //compared against material hardness, stone is 4 and bedrock 255
+const HEALTH_BAR_DIM: (usize, usize) = (200, 16);
+const BED_DIM: (isize, isize) = (16, 6);    //size of the block of bed a bed item places
+const FRAME_EVERY: usize = 60;          //ticks between frames written by --headless --frames
 
 struct Mouse {
-    coords: (isize, isize),
+    coords: (isize, isize),             //screen coords
     sprite: sprite::Sprite,
+    brush_radius: isize,                //radius of circle placed, or dug with bare hands
+    mining_at: (isize, isize),          //world coords digging started at, moving the brush off them starts over
+    strength: f32,                      //built up digging in one spot, compared against material hardness
+    progress: f32,                      //0 to 1 towards digging the next particle, drawn under the cursor
 }
 
 impl Mouse {
@@ -27,26 +55,67 @@ impl Mouse {
         Self {
             coords: (0,0),
             sprite: sprite::Sprite::load("sprites/mouse.png").unwrap().scale(4),
+            brush_radius: BRUSH_RADIUS,
+            mining_at: (0,0),
+            strength: 0.0,
+            progress: 0.0,
         }
     }
 }
 
+///where a frame is drawn from, positions already smoothed out between ticks
+struct View {
+    camera_coords: (isize, isize),
+    player_coords: (isize, isize),      //where the player is drawn, not where it collides
+    debug_fps: Option<usize>,           //fps for the debug screen, none if its hidden
+}
+
 
 fn main() {
-    let mut seed = 0;
-    let world = gen::World::new_perlin(CHUNK_DIM, &mut seed, SET_SEED, GEN_RANGE);                                          //generate world
-    let mut screen= drawing::Screen::new(SCREEN_DIM.0, SCREEN_DIM.1);                                                       //create blank screen buffer
-    let mut player = player::Player::spawn((0,0), sprite::Spritesheet::load("sprites/america.gif", 500).unwrap());          //spawn player at 0,0
-    let mut camera_coords: (isize, isize) = (0-(SCREEN_DIM.0 as isize/2),0+(SCREEN_DIM.1 as isize/2));                      //set camera location
-    let mut mouse = Mouse::new();
+    let mut config = match get_config() {                                                                                   //read config.toml and command line overrides
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        },
+    };
+    if std::env::args().any(|a| a == "--headless" || a == "--replay") {                                                    //run without a window
+        if let Err(e) = run_headless(&config) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut record = get_arg("--record").unwrap();                                                                          //file to write a replay of this session to
+    let mut session = record.as_ref().map(|_| {                                                                             //recordings skip the menus and start a fresh world so they can be replayed from the seed
+        let mut s = Session::new(config.seed.unwrap_or_else(rand::random), get_generator_arg().unwrap(), &config).unwrap();
+        s.start_recording();
+        s
+    });
+    let mut states = vec![state::State::MainMenu(state::Menu::default())];                                                  //main menu is always at the bottom
+    if session.is_some() {states.push(state::State::Playing)}
+    let mut screen= drawing::Screen::new(config.screen_dim.0, config.screen_dim.1);                                         //create blank screen buffer
+    let mut title_sprite = sprite::Spritesheet::load("sprites/america.gif", 500).unwrap();                                  //shown on the main menu
+    let cursor_sprite = sprite::Sprite::load("sprites/mouse.png").unwrap().scale(4);
+    let mut cursor = (0,0);                                                                                                 //screen coords of the mouse
+    let mut bindings = bindings::Bindings::load(CONTROLS_FILE).unwrap_or_else(|e| {                                        //which keys do what
+        eprintln!("{}, using the default controls", e);                                                                     //a broken controls file shouldnt stop the game starting
+        bindings::Bindings::defaults()
+    });
     let mut debug_flag = false;
+    let mut scrolled = 0;                                                                                                   //wheel turned since the last tick, held over frames that dont tick
+    let mut respawning = false;                                                                                             //death screen asked to respawn and no tick has run since
+    let mut view_distance = 0;                                                                                              //changed in settings and not sent with a tick yet
+    let mut swaps = Vec::new();                                                                                             //inventory moves waiting for a tick, one goes with each
 
-    let mut fpslock = game::FpsLock::create_lock(TARGET_FPS);                                                               //create fps lock obj
+    let mut fpslock = game::FpsLock::create_lock(config.fps);                                                               //create fps lock obj
+    let mut timestep = timestep::Timestep::new(TICK_RATE, MAX_CATCH_UP);                                                    //decides when to tick
 
     let event_loop = game::EventLoop::new();                                                                                //create event loop obj
     let mut input = game::WinitInputHelper::new();                                                                          //create input helper obj
-    let mut window = game::Window::init(GAME_TITLE, SCREEN_DIM.0, SCREEN_DIM.1, &event_loop);                               //create window, and pixels buffer
-    window.fullscreen();
+    let mut window = game::Window::init(GAME_TITLE, config.screen_dim.0, config.screen_dim.1, &event_loop);                 //create window, and pixels buffer
+    if config.fullscreen {window.fullscreen()}
     window.window.set_cursor_visible(false);
 
 
@@ -54,7 +123,22 @@ fn main() {
     event_loop.run(move |event, _, control_flow| {                                                                          //start game loop
         fpslock.start_frame();                                                                                              //start frame for fps lock
         if let game::Event::RedrawRequested(_) = event {                                                                    //if redraw requested
-            draw_screen(&mut screen, &world, &mut player, camera_coords, debug_flag, fpslock.get_fps(), seed, &mouse);      //draws new frame to screen buffer
+            match &mut session {
+                Some(s) => {                                                                                                //the world goes under every state while its open
+                    let alpha = timestep.alpha();                                                                           //how far into the next tick we are
+                    let view = View {
+                        camera_coords: timestep::lerp(s.last_camera, s.camera_coords, alpha),
+                        player_coords: timestep::lerp(s.player.last_coords, s.player.coords, alpha),
+                        debug_fps: if debug_flag {Some(fpslock.get_fps())} else {None},
+                    };
+                    draw_screen(&mut screen, &s.world, &mut s.player, &view, &s.config, &s.mouse);                     //draws new frame to screen buffer
+                },
+                None => screen.buf.iter_mut().for_each(|b| *b = 0),                                                         //menus without a world go on black
+            }
+            let state = states.last().unwrap();
+            let inventory = session.as_ref().map(|s| (&s.player.inventory, &s.world.registry));
+            state.draw(&mut screen, &config, &bindings, GAME_TITLE, &title_sprite.get_sprite(), inventory);               //draw menus on top
+            if *state != state::State::Playing {screen.draw_sprite(&cursor_sprite, cursor)}                                 //cursor over the dimmed menu
             screen.flatten(window.pixels.get_frame());                                                                      //flatten screen to 1D for render
             window.pixels.render().unwrap();                                                                                //render
 
@@ -63,20 +147,21 @@ fn main() {
 
         if input.update(event) {                                                                                            //handle input events on loop? not just on event
 
-            if input.key_pressed(game::VirtualKeyCode::Escape) || input.quit() {                                            //if esc pressed
+            if input.quit() {                                                                                               //if window closed
+                leave_world(&mut session, &mut record);
                 *control_flow = game::ControlFlow::Exit;                                                                    //exit
                 return;
             }
 
-            if input.key_held(game::VirtualKeyCode::W) {player.walk(player::Direction::Up)}
-            if input.key_held(game::VirtualKeyCode::A) {player.walk(player::Direction::Left)}
-            if input.key_held(game::VirtualKeyCode::S) {player.walk(player::Direction::Down)}
-            if input.key_held(game::VirtualKeyCode::D) {player.walk(player::Direction::Right)}
-            if input.key_pressed(game::VirtualKeyCode::Space){player.jump()}
-            if input.key_pressed(game::VirtualKeyCode::LShift) {player.running = true}
-            else if input.key_released(game::VirtualKeyCode::LShift){ player.running = false}
-            if input.key_pressed(game::VirtualKeyCode::F3) {debug_flag = !debug_flag}
-            if let Some(m) = input.mouse() {mouse.coords = (m.0 as isize, m.1 as isize)};
+            let rebinding = matches!(states.last(), Some(state::State::Controls(_, true)));                                //keys pressed while rebinding only get bound
+            if bindings.pressed(bindings::Action::ToggleDebug, &input) && !rebinding {debug_flag = !debug_flag}
+            if bindings.pressed(bindings::Action::ReloadControls, &input) && !rebinding {                                  //pick up edits to the controls file
+                match bindings::Bindings::load(CONTROLS_FILE) {
+                    Ok(b) => bindings = b,
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+            if let Some(m) = input.mouse() {cursor = (m.0 as isize, m.1 as isize)};
 
             if let Some(factor) = input.scale_factor_changed() {                                                            //if window dimensions changed
                 window.hidpi_factor = factor;                                                                               //update hidpi_factor
@@ -85,18 +170,125 @@ fn main() {
                 window.pixels.resize(size.width, size.height);                                                              //resize pixel aspect ratio
             }
 
-            do_updates(&mut camera_coords, &mut player, &world);
+            let settings = config.clone();
+            let transition = states.last_mut().unwrap().update(&bindings.menu_input(&input, cursor), &mut config);        //let the top state handle input
+            match transition {
+                state::Transition::None => {},
+                state::Transition::Push(state) => states.push(state),
+                state::Transition::Pop => {states.pop();},
+                state::Transition::Open(name) => match open_world(&format!("{}/{}", save::SAVES_DIR, name), None, &config) {
+                    Ok(s) => {session = Some(s); states = vec![states.remove(0), state::State::Playing]},
+                    Err(e) => eprintln!("couldn't open world {}: {}", name, e),
+                },
+                state::Transition::Create(seed) => match open_world(&new_world_path(), seed, &config) {
+                    Ok(s) => {session = Some(s); states = vec![states.remove(0), state::State::Playing]},
+                    Err(e) => eprintln!("couldn't create world: {}", e),
+                },
+                state::Transition::Respawn => {
+                    respawning = true;                                                                                      //sent with the next tick so replays respawn too
+                    states.pop();
+                },
+                state::Transition::SwapSlots(a, b) => swaps.push((a as u8, b as u8)),                                      //sent with the next ticks so replays move things too
+                state::Transition::SaveConfig => {
+                    let path = get_arg("--config").unwrap().unwrap_or_else(|| config::CONFIG_FILE.to_string());
+                    if let Err(e) = config.save(&path) {eprintln!("{}", e)}
+                },
+                state::Transition::Bind(action, input) => {
+                    bindings.bind(action, input);
+                    if let Err(e) = bindings.save(CONTROLS_FILE) {eprintln!("{}", e)}
+                },
+                state::Transition::Unbind(action) => {
+                    bindings.clear(action);
+                    if let Err(e) = bindings.save(CONTROLS_FILE) {eprintln!("{}", e)}
+                },
+                state::Transition::ToMenu => {
+                    leave_world(&mut session, &mut record);
+                    states.truncate(1);
+                },
+                state::Transition::Quit => {
+                    leave_world(&mut session, &mut record);
+                    *control_flow = game::ControlFlow::Exit;
+                    return;
+                },
+            }
+            if config != settings {                                                                                         //settings menu changed something
+                fpslock = game::FpsLock::create_lock(config.fps);
+                if let Some(s) = &mut session {s.configure(&config)}
+                if config.gen_range != settings.gen_range {view_distance = config.gen_range as u8}                           //sent with the next tick so replays load the same chunks
+            }
+
+            let due = timestep.advance();                                                                                   //time keeps passing in menus so theres nothing to catch up on after
+            let inventory_open = matches!(states.last(), Some(state::State::Inventory(..)));
+            let ticking = inventory_open || states.last() == Some(&state::State::Playing);                                 //the world only stops for menus, not the inventory
+            if let (Some(s), true) = (&mut session, ticking) {
+                let mut controls = match inventory_open {
+                    true => script::Controls {cursor, ..Default::default()},                                                //the player stands still while moving things around
+                    false => bindings.controls(&input, cursor),                                                             //read what the player is holding
+                };
+                scrolled += controls.scroll;
+                for _ in 0..due {                                                                                           //run every tick thats due
+                    controls.scroll = std::mem::take(&mut scrolled);                                                        //each turn of the wheel only moves the hotbar once
+                    controls.respawn = std::mem::take(&mut respawning);
+                    controls.view_distance = std::mem::take(&mut view_distance);
+                    controls.swap = if swaps.is_empty() {None} else {Some(swaps.remove(0))};
+                    s.step(controls);
+                    if s.player.health == 0 {                                                                               //died
+                        let cause = s.player.last_damage.map(|d| d.describe(&s.world.registry)).unwrap_or_default();
+                        game_over(&mut states, cause);
+                        break;
+                    }
+                }
+            } else {
+                title_sprite.update();
+            }
             window.window.request_redraw();                                                                                 //request frame redraw
         }
     });
 }
 
+///opens a save directory and starts playing the world in it, creating it if its new
+///seed is only used for new worlds, random if none
+fn open_world(path: &str, seed: Option<u32>, config: &config::Config) -> Result<Session, String> {
+    let save = save::SaveDir::open(path).map_err(|e| e.to_string())?;                                                      //its seed and generator override ours if it has them
+    let mut session = Session::start(seed, get_generator_arg()?, Some(save), config)?;
+    session.world.start_workers(WORKER_THREADS, session.config.chunk_dim);                                                  //generate chunks in the background so it opens straight away
+    session.world.save(session.config.chunk_dim).map_err(|e| e.to_string())?;                                               //write the meta straight away so it shows up in the world list
+    Ok(session)
+}
+
+///first save directory named world, world_2, world_3... thats free
+fn new_world_path() -> String {
+    let mut path = format!("{}/world", save::SAVES_DIR);
+    let mut n = 1;
+    while std::path::Path::new(&path).exists() {
+        n += 1;
+        path = format!("{}/world_{}", save::SAVES_DIR, n);
+    }
+    path
+}
+
+///saves and closes the world being played, writing its replay if it was recorded
+fn leave_world(session: &mut Option<Session>, record: &mut Option<String>) {
+    if let Some(mut s) = session.take() {
+        if let Err(e) = s.world.save(s.config.chunk_dim) {eprintln!("couldn't save world: {}", e)}                          //save changes
+        if let (Some(replay), Some(path)) = (s.replay(), record.take()) {                                                   //only the first world is recorded
+            if let Err(e) = replay.save(&path) {eprintln!("{}", e)}
+        }
+    }
+}
 
 
+
+///shows the death screen straight over the world, closing the inventory or anything else open on top of it
+fn game_over(states: &mut Vec<state::State>, cause: String) {
+    if let Some(playing) = states.iter().position(|s| *s == state::State::Playing) {states.truncate(playing + 1)}
+    states.push(state::State::GameOver(state::Menu::default(), cause));
+}
+
 ///updates camera position based off player coords
-fn update_camera(camera_coords: &mut (isize,isize), player: &mut player::Player) {
-    let distance_x = player.coords.0 - camera_coords.0 - (SCREEN_DIM.0 as isize/2) + (player.sprite.get_dimensions().0/2); //calc x coord distance
-    let distance_y = player.coords.1 - camera_coords.1 + (SCREEN_DIM.1 as isize/2) - (player.sprite.get_dimensions().1/2); //calc y coord distance
+fn update_camera(camera_coords: &mut (isize,isize), player: &mut player::Player, screen_dim: (usize, usize)) {
+    let distance_x = player.coords.0 - camera_coords.0 - (screen_dim.0 as isize/2) + (player.sprite.get_dimensions().0/2); //calc x coord distance
+    let distance_y = player.coords.1 - camera_coords.1 + (screen_dim.1 as isize/2) - (player.sprite.get_dimensions().1/2); //calc y coord distance
     let move_cam = |distance, camera: &mut isize| {                                 //closure that handles moving camera                                    KINDA JANK
         if distance < 25 && distance > -25 && distance != 0 {                       //if camera distance less than 25px from player and not on player
             if distance >= 0 {*camera+=1}                                           //move 1px positive if positive
@@ -110,27 +302,307 @@ fn update_camera(camera_coords: &mut (isize,isize), player: &mut player::Player)
 
 
 
+///a world being played, fed controls one tick at a time. used by the window, --headless, --replay and tests
+struct Session {
+    world: gen::World,
+    player: player::Player,
+    camera_coords: (isize, isize),
+    last_camera: (isize, isize),        //camera location last tick, drawn between the two
+    mouse: Mouse,
+    last: script::Controls,             //controls held last tick
+    recording: Option<script::Replay>,  //every tick played so far, only kept when asked for since it grows every time the cursor moves
+    config: config::Config,             //with the chunk size of the save if it has one
+}
+
+impl Session {
+    ///starts a fresh unsaved world. chunks generate on this thread so the same controls always play out the same
+    fn new(seed: u32, generator: generator::GeneratorKind, config: &config::Config) -> Result<Self, String> {
+        Session::start(Some(seed), generator, None, config)
+    }
+
+    ///starts playing a world, random seed if none. a saved world keeps its own seed, generator and chunk size
+    fn start(seed: Option<u32>, generator: generator::GeneratorKind, save: Option<save::SaveDir>, config: &config::Config) -> Result<Self, String> {
+        let mut config = config.clone();
+        if let Some(size) = save.as_ref().and_then(|s| s.chunk_size) {                                      //regions only load with the chunk size they were saved with
+            config.chunk_dim = (size, size);
+            config.validate()?;
+        }
+        let mut seed_value = seed.unwrap_or(0);
+        let registry = material::Registry::load(MATERIAL_FILE).map_err(|e| e.to_string())?;
+        let mut world = gen::World::new(&mut seed_value, seed.is_some(), config.gen_range, registry, generator, save)?;
+        let spawn = world.spawn_point();
+        world.update_chunks(spawn, config.chunk_dim);                                                       //queue chunks around spawn nearest first
+        let camera_coords = (spawn.0-(config.screen_dim.0 as isize/2),spawn.1+(config.screen_dim.1 as isize/2));
+        Ok(Self {
+            world,
+            player: player::Player::spawn(spawn, sprite::Spritesheet::load("sprites/america.gif", 500).map_err(|e| e.to_string())?),
+            camera_coords,
+            last_camera: camera_coords,
+            mouse: Mouse::new(),
+            last: script::Controls::default(),
+            recording: None,
+            config,
+        })
+    }
+
+    fn step(&mut self, controls: script::Controls) {
+        self.last_camera = self.camera_coords;
+        let dead = self.player.health == 0;
+        tick(&controls, &self.last, &mut self.camera_coords, &mut self.player, &mut self.world, &mut self.mouse, &self.config);
+        if dead {self.last_camera = self.camera_coords}                                                     //jump straight to where they respawned
+        self.last = controls;
+        if let Some(r) = &mut self.recording {r.script.push(controls)}
+    }
+
+    ///takes on settings changed while playing, except chunk size which is fixed once a world is open
+    ///and view distance which changes what gets simulated, so it only reaches the world through a tick
+    fn configure(&mut self, config: &config::Config) {
+        self.config = config::Config {chunk_dim: self.config.chunk_dim, gen_range: self.config.gen_range, ..config.clone()};
+    }
+
+    ///starts keeping every tick played from here on, with the settings needed to play them back
+    fn start_recording(&mut self) {
+        self.recording = Some(script::Replay {
+            seed: self.world.seed,
+            generator: self.world.generator_kind.to_string(),
+            screen_dim: self.config.screen_dim,
+            chunk_dim: self.config.chunk_dim,
+            gen_range: self.world.gen_range,
+            debug: self.config.debug,
+            script: script::Script::default(),
+        });
+    }
+
+    ///the replay of everything played since recording started, if it has
+    fn replay(&self) -> Option<&script::Replay> {
+        self.recording.as_ref()
+    }
+}
+
+///runs the game for a number of ticks without a window, driven by an input script, then prints where it ended up
+///    --ticks <n>          how long to run, defaults to the length of the script
+///    --script <file>      controls to play back, see script::Script
+///    --replay <file>      plays a recorded session back in the world it was recorded in instead
+///    --record <file>      writes what was played as a replay
+///    --frames <dir>       writes a png of the screen every --frame-every ticks (default FRAME_EVERY)
+///the world uses the configured seed, or 0 if there isnt one, so runs without --seed still match
+///chunks generate on this thread and nothing is saved, so the same arguments always print the same thing
+///a player that dies stays where they fell with the world stopped until the script holds respawn, like the death screen
+fn run_headless(config: &config::Config) -> Result<(), String> {
+    let (seed, generator, script, config) = match get_arg("--replay")? {
+        Some(path) => {
+            let replay = script::Replay::load(&path)?;
+            (replay.seed, replay.generator.parse()?, replay.script.clone(), replay.config(config)?)       //played with the settings it was recorded with
+        },
+        None => {
+            let script = match get_arg("--script")? {
+                Some(path) => script::Script::load(&path)?,
+                None => script::Script::default(),
+            };
+            (config.seed.unwrap_or(0), get_generator_arg()?, script, config.clone())
+        },
+    };
+    let config = &config;
+    let ticks = match get_arg("--ticks")? {
+        Some(t) => t.parse().map_err(|_| format!("--ticks needs a number, not {}", t))?,
+        None => script.ticks(),
+    };
+    let frames = get_arg("--frames")?;
+    let frame_every = match get_arg("--frame-every")? {
+        Some(n) => n.parse().ok().filter(|n| *n > 0).ok_or_else(|| format!("--frame-every needs a number above 0, not {}", n))?,
+        None => FRAME_EVERY,
+    };
+
+    let record = get_arg("--record")?;
+    let mut session = Session::new(seed, generator, config)?;
+    if record.is_some() {session.start_recording()}
+    let mut screen = drawing::Screen::new(config.screen_dim.0, config.screen_dim.1);
+    if let Some(dir) = &frames {std::fs::create_dir_all(dir).map_err(|e| format!("couldn't create {}: {}", dir, e))?}
+    for t in 0..ticks {
+        session.step(script.get(t));
+        if let (Some(dir), true) = (&frames, (t+1) % frame_every == 0) {                                    //write a frame
+            let view = View {camera_coords: session.camera_coords, player_coords: session.player.coords, debug_fps: None};
+            draw_screen(&mut screen, &session.world, &mut session.player, &view, config, &session.mouse);
+            let mut rgba = screen.buf.clone();
+            rgba.chunks_exact_mut(4).for_each(|p| p[3] = 255);                                              //air is see through on screen but shouldnt be in the png
+            let path = format!("{}/frame_{:06}.png", dir, t+1);
+            image::save_buffer(&path, &rgba, config.screen_dim.0 as u32, config.screen_dim.1 as u32, image::ColorType::Rgba8).map_err(|e| format!("couldn't write {}: {}", path, e))?;
+        }
+    }
+    if let (Some(path), Some(replay)) = (record, session.replay()) {replay.save(&path)?}
+
+    println!("tick {}", session.world.tick);
+    println!("player {} {}", session.player.coords.0, session.player.coords.1);
+    println!("velocity {:.3} {:.3}", session.player.velocity.0, session.player.velocity.1);
+    println!("health {}", session.player.health);
+    println!("world {:016x}", session.world.data_hash());
+    Ok(())
+}
+
+///applies one ticks worth of controls then moves the game on a tick
+///nothing happens while the player is dead until a tick with respawn, like the window stops ticking on the death screen
+fn tick(controls: &script::Controls, last: &script::Controls, camera_coords: &mut (isize, isize), player: &mut player::Player, world: &mut gen::World, mouse: &mut Mouse, config: &config::Config) {
+    if player.health == 0 {
+        if !controls.respawn {return}
+        respawn(camera_coords, player, world, config);
+    }
+    let pressed = controls.pressed(last);
+    mouse.coords = controls.cursor;
+    if config.debug && pressed.noclip {player.noclip = !player.noclip}                                 //toggle debug noclip
+    if controls.up && player.noclip {player.walk(player::Direction::Up)}                                //only fly in noclip
+    if controls.left {player.walk(player::Direction::Left)}
+    if controls.down && player.noclip {player.walk(player::Direction::Down)}
+    if controls.right {player.walk(player::Direction::Right)}
+    if pressed.jump {player.jump()}
+    else if last.jump && !controls.jump {player.release_jump()}                                         //short hop if let go early
+    player.running = controls.run;
+    if pressed.shrink_brush {mouse.brush_radius = (mouse.brush_radius - 1).max(1)}
+    if pressed.grow_brush {mouse.brush_radius = (mouse.brush_radius + 1).min(MAX_BRUSH_RADIUS)}
+    if pressed.prev_slot {player.inventory.scroll(-1)}
+    if pressed.next_slot {player.inventory.scroll(1)}
+    if pressed.scroll != 0 {player.inventory.scroll(pressed.scroll)}
+    if pressed.slot != 0 {player.inventory.select(pressed.slot as usize - 1)}
+    if let Some((a, b)) = pressed.swap {player.inventory.swap(a as usize, b as usize)}
+    if pressed.view_distance != 0 {world.gen_range = pressed.view_distance as isize}
+
+    let cursor = screen_to_world(controls.cursor, *camera_coords);
+    let (min, max) = player.bounds();
+    let outside_player = |(x, y): (isize, isize)| x < min.0 || x > max.0 || y < min.1 || y > max.1;   //dont bury the player
+    let selected = player.inventory.selected;
+    let (tier, power, brush) = match player.inventory.held() {
+        Some(inventory::Stack {item: inventory::Item::Tool(tool, _), ..}) => (tool.tier(), tool.power(), tool.brush()),
+        _ => (tool::HANDS_TIER, tool::HANDS_POWER, (mouse.brush_radius, mouse.brush_radius)),           //hands dig a circle the size of the brush
+    };
+    let offset = (cursor.0 - mouse.mining_at.0, cursor.1 - mouse.mining_at.1);
+    if !controls.dig || !edit::in_ellipse(offset, brush) {                                              //stopped or moved away, start over. drifting with the camera is fine
+        mouse.mining_at = cursor;
+        mouse.strength = 0.0;
+        mouse.progress = 0.0;
+    }
+    if controls.dig {                                                                                   //if left click dig under cursor with whatever is in hand
+        mouse.strength += power;
+        let (removed, next) = world.mine(cursor, brush, tier, mouse.strength, config.chunk_dim);
+        if next.is_some() || !removed.is_empty() {player.inventory.wear(selected)}                     //only wears out digging something it can
+        mouse.progress = match next {
+            Some(needed) => mouse.strength / needed,
+            None => {mouse.strength = 0.0; 0.0},                                                        //nothing left it can dig
+        };
+        let mut removed: Vec<_> = removed.into_iter().collect();
+        removed.sort_unstable();                                                                        //same slots every run no matter the map order
+        for (material, count) in removed {player.inventory.add(inventory::Item::Material(material), count);}   //whatever doesnt fit is lost
+    } else if controls.place {                                                                          //if right click build the held item under cursor
+        match player.inventory.held() {
+            Some(inventory::Stack {item: inventory::Item::Material(material), count}) => {
+                let placed = world.place(cursor, mouse.brush_radius, material, count, config.chunk_dim, outside_player);
+                player.inventory.take(selected, placed);
+            },
+            Some(inventory::Stack {item: inventory::Item::Bed, ..}) if pressed.place => {            //one bed per click, standing on the cursor
+                if let Some(bed) = world.registry.id("bed") {
+                    let corner = (cursor.0 - BED_DIM.0/2, cursor.1);
+                    let placed = world.place_rect(corner, (corner.0 + BED_DIM.0 - 1, corner.1 + BED_DIM.1 - 1), bed, config.chunk_dim, outside_player);
+                    if placed > 0 {player.inventory.take(selected, 1);}
+                }
+            },
+            _ => {},
+        }
+    }
+    if pressed.explode && player.inventory.remove(inventory::Item::Bomb, 1) == 1 {                     //if middle click blow up under cursor, using up a bomb
+        world.explode(cursor, EXPLOSION_RADIUS, EXPLOSION_POWER, config.chunk_dim);
+        player.blast(cursor, EXPLOSION_RADIUS, EXPLOSION_POWER);                                         //hurting and throwing the player if its close
+    }
+    do_updates(camera_coords, player, world, config);
+}
+
+///puts the player and camera back at their bed, or the world spawn if they havent touched one or it was destroyed
+fn respawn(camera_coords: &mut (isize, isize), player: &mut player::Player, world: &gen::World, config: &config::Config) {
+    let bed = player.bed.filter(|b| match world.get_particle(*b, config.chunk_dim) {
+        Some(p) => world.registry.get(p.material()).respawn,
+        None => true,                                                                                   //too far away to be loaded, trust its still there
+    });
+    let spawn = match bed {
+        Some(b) => player.standing_on(b),
+        None => world.spawn_point(),
+    };
+    player.bed = bed;
+    player.respawn(spawn);
+    *camera_coords = (spawn.0-(config.screen_dim.0 as isize/2),spawn.1+(config.screen_dim.1 as isize/2));
+}
+
+///gets the value after a flag on the command line, if the flag is there
+fn get_arg(flag: &str) -> Result<Option<String>, String> {
+    let args: Vec<String> = std::env::args().collect();
+    match args.iter().position(|a| a == flag) {
+        Some(i) => args.get(i+1).cloned().map(Some).ok_or_else(|| format!("{} needs a value", flag)),
+        None => Ok(None),
+    }
+}
+
+///reads the generator from --generator on the command line, falling back to DEFAULT_GENERATOR
+fn get_generator_arg() -> Result<generator::GeneratorKind, String> {
+    get_arg("--generator")?.as_deref().unwrap_or(DEFAULT_GENERATOR).parse()
+}
+
+///reads config.toml, or the file after --config, then applies the settings given on the command line
+fn get_config() -> Result<config::Config, String> {
+    let mut config = match get_arg("--config")? {
+        Some(path) => config::Config::load(&path, true)?,
+        None => config::Config::load(config::CONFIG_FILE, false)?,
+    };
+    config.apply_args(&std::env::args().collect::<Vec<String>>())?;
+    Ok(config)
+}
+
+///converts screen coords to the world coords drawn there
+fn screen_to_world(screen_coords: (isize, isize), camera_coords: (isize, isize)) -> (isize, isize) {
+    (camera_coords.0 + screen_coords.0, camera_coords.1 - screen_coords.1)
+}
+
 ///gets 2D vec of current frame to draw from 4D Vec
-fn draw_screen(screen: &mut drawing::Screen, world: &gen::World, player: &mut player::Player, camera_coords: (isize, isize), debug_flag: bool, fps: usize, seed: u32, mouse: &Mouse) {
-    world.get_screen(&mut screen.buf, camera_coords, SCREEN_DIM, CHUNK_DIM);                            //gets visible pixels from world as 2d vec
-    screen.draw_sprite(&player.sprite.get_sprite(), screen.get_coords(player.coords, camera_coords));   //draw player sprite
-    if ENABLE_DEBUG && debug_flag {                                                                     //if debug flag and debug enabled:
-        screen.draw_debug_block(screen.get_coords((camera_coords.0 + (SCREEN_DIM.0 as isize/2), camera_coords.1 - (SCREEN_DIM.1 as isize/2)), camera_coords), 5, &[255;4]);           //render debug block on camera INACCURATE
+fn draw_screen(screen: &mut drawing::Screen, world: &gen::World, player: &mut player::Player, view: &View, config: &config::Config, mouse: &Mouse) {
+    let screen_dim = config.screen_dim;
+    let (camera_coords, player_coords) = (view.camera_coords, view.player_coords);
+    world.get_screen(&mut screen.buf, camera_coords, screen_dim, config.chunk_dim);                            //gets visible pixels from world as 2d vec
+    screen.draw_sprite(&player.sprite.get_sprite(), screen.get_coords(player_coords, camera_coords));   //draw player sprite
+    if let (true, Some(fps)) = (config.debug, view.debug_fps) {                                         //if debug flag and debug enabled:
+        screen.draw_debug_block(screen.get_coords((camera_coords.0 + (screen_dim.0 as isize/2), camera_coords.1 - (screen_dim.1 as isize/2)), camera_coords), 5, &[255;4]);           //render debug block on camera INACCURATE
         //screen.draw_debug_box(screen.get_coords(player.coords, camera_coords),                          //render debug outline on player
         //                        (player.sprite.get_sprite().width,
         //                        player.sprite.get_sprite().height),
         //                        [255,0,0,0]);
-        let hitbox = player.sprite.get_hitbox(player.coords).iter().map(|(x,y)| screen.get_coords((*x,*y), camera_coords)).collect::<Vec<(isize,isize)>>(); //calc hitbox on screen
+        let hitbox = player.sprite.get_hitbox(player_coords).iter().map(|(x,y)| screen.get_coords((*x,*y), camera_coords)).collect::<Vec<(isize,isize)>>(); //calc hitbox on screen
         screen.draw_hitbox(hitbox, &[0,255,0,0]);                                                        //draw hitbox
-        draw_debug_screen(screen, player, camera_coords, fps, seed, CHUNK_DIM)                          //render debug screen
+        draw_debug_screen(screen, world, player, camera_coords, fps, world.seed, config.chunk_dim)      //render debug screen
     }
-    screen.draw_text((20,SCREEN_DIM.1-30), GAME_TITLE, 32.0, &[255,255,255,0], drawing::DEBUG_FONT);     //render game title
+    draw_health_bar(screen, (20,screen_dim.1-100), player.health);                                    //render health
+    state::draw_hotbar(screen, &player.inventory, &world.registry);                                    //render hotbar
+    let held = match player.inventory.held() {
+        Some(inventory::Stack {item: inventory::Item::Tool(tool, left), ..}) => format!("{} {}/{}", tool.name(), left, tool.durability()),
+        Some(stack) => format!("{} x{}", stack.item.name(&world.registry), stack.count),
+        None => "nothing".to_string(),
+    };
+    let brush = format!("Holding: {} ({}px)", held, mouse.brush_radius);
+    screen.draw_text((20,screen_dim.1-60), &brush, 32.0, &[255,255,255,0], drawing::DEBUG_FONT);       //render held item
+    screen.draw_text((20,screen_dim.1-30), GAME_TITLE, 32.0, &[255,255,255,0], drawing::DEBUG_FONT);     //render game title
     screen.draw_sprite(&mouse.sprite, mouse.coords);                                                    //draw mouse
+    if mouse.progress > 0.0 {                                                                           //with how far the dig has got under it
+        let coords = (mouse.coords.0.max(0) as usize, mouse.coords.1.max(0) as usize + mouse.sprite.height + 4);
+        state::fill_rect(screen, coords, (mouse.sprite.width, 4), [40,40,40,255]);
+        state::fill_rect(screen, coords, ((mouse.sprite.width as f32 * mouse.progress) as usize, 4), [255,255,255,255]);
+    }
+}
+
+
+///draws a bar filled with how much of player::MAX_HEALTH is left, with the number next to it
+fn draw_health_bar(screen: &mut drawing::Screen, coords: (usize, usize), health: usize) {
+    let filled = HEALTH_BAR_DIM.0 * health.min(player::MAX_HEALTH) / player::MAX_HEALTH;
+    state::fill_rect(screen, coords, HEALTH_BAR_DIM, [40,40,40,255]);
+    state::fill_rect(screen, coords, (filled, HEALTH_BAR_DIM.1), [200,30,30,255]);                    //red for whats left over grey
+    screen.draw_text((coords.0+HEALTH_BAR_DIM.0+10, coords.1), &health.to_string(), 32.0, &[255,255,255,0], drawing::DEBUG_FONT);
 }
 
 
 ///draws debug text
-pub fn draw_debug_screen(screen: &mut drawing::Screen, player: &mut player::Player, camera_coords: (isize,isize), fps: usize, seed: u32, chunk_dim: (usize, usize)) {
+pub fn draw_debug_screen(screen: &mut drawing::Screen, world: &gen::World, player: &mut player::Player, camera_coords: (isize,isize), fps: usize, seed: u32, chunk_dim: (usize, usize)) {
     let size = 32.0;
     let color = &[255,0,0,0];
     screen.draw_text((20,20), "DEBUG", size, color, drawing::DEBUG_FONT);
@@ -140,31 +612,40 @@ pub fn draw_debug_screen(screen: &mut drawing::Screen, player: &mut player::Play
     screen.draw_text((20,60), &s, size, color, drawing::DEBUG_FONT);
     let s = format!("Velocity: {:2.3}, {:2.3}", player.velocity.0, player.velocity.1);
     screen.draw_text((20,80), &s, size, color, drawing::DEBUG_FONT);
-    let s = format!("Chunk: {}, {} in {}, {}", player.coords.0 % chunk_dim.0 as isize, player.coords.1 % chunk_dim.0 as isize,
-                                            player.coords.0 / chunk_dim.0 as isize, player.coords.1 / chunk_dim.0 as isize,);
+    let s = format!("Grounded: {}{}", player.on_ground, if player.noclip {" (noclip)"} else {""});
+    screen.draw_text((20,180), &s, size, color, drawing::DEBUG_FONT);
+    let (chunk, inner) = gen::World::get_chunk_coords(player.coords, chunk_dim);
+    let s = format!("Chunk: {}, {} in {}, {} ({} loaded)", inner.0, inner.1, chunk.0, chunk.1, world.data.len());
     screen.draw_text((20,100), &s, size, color, drawing::DEBUG_FONT);
     let s = format!("Camera: {}, {}", camera_coords.0, camera_coords.1);
     screen.draw_text((20,120), &s, size, color, drawing::DEBUG_FONT);
     let s = format!("Seed: {}", seed);
     screen.draw_text((20,140), &s, size, color, drawing::DEBUG_FONT);
+    if let Some(biome) = world.biome_at(player.coords.0) {
+        let s = format!("Biome: {}", biome);
+        screen.draw_text((20,160), &s, size, color, drawing::DEBUG_FONT);
+    }
 }
 
 
-fn do_updates(camera_coords: &mut (isize, isize), player: &mut player::Player, world: &gen::World) {
-    player.update_location(&world, CHUNK_DIM);                                                          //update player location
+fn do_updates(camera_coords: &mut (isize, isize), player: &mut player::Player, world: &mut gen::World, config: &config::Config) {
+    world.update_chunks(player.coords, config.chunk_dim);                                               //stream chunks around player
+    world.simulate(config.chunk_dim);                                                                   //move particles in active chunks
+    player.update_location(world, config.chunk_dim);                                                    //update player location
+    player.update_health(world, config.chunk_dim);                                                      //hazards, suffocation and beds
     player.sprite.update();
-    update_camera(camera_coords, player);                                                               //move camera towards player
+    update_camera(camera_coords, player, config.screen_dim);                                                               //move camera towards player
 }
 
 
+//World
+//    data: HashMap<(isize,isize), Chunk>
 //Chunk
 //    coords: (i32,i32),
-//    data: Vec<Vec<Particle>>
-//         coords: (u8,u8)
-//         data: Particle
-//             rgba: [u8;4]
+//    data: Vec<Particle>
+//         Particle: u16 material id << 3 | shade
+//    state: Option<Vec<CellState>>
 
-//loaded = index in world vec
 //world = world coords
 //chunk = chunk index
 //inner = local index within chunk
@@ -188,6 +669,8 @@ fn do_updates(camera_coords: &mut (isize, isize), player: &mut player::Player, w
 
 //de-optimized when i passed get_screen non constant chunk dimensions. caused full div operations to be compiled and ruined inlining
 //re-optimized by changing chunk and screen coords to tuples and passing directly instead of in struct
+//chunk dims come from config now, kept fast by forcing them to powers of two so get_chunk_coords is a shift and mask not a div
+//the sim loop and generators split chunk indices the same way with get_inner_coords, bench_simulate 11.4ms -> 10.5ms
 
 
 
@@ -198,24 +681,26 @@ mod tests {
 
     #[bench]
     fn bench_draw_screen(b: &mut Bencher) {
+        let config = test::black_box(config::Config::default());
         let mut seed = 0;
-        let world = gen::World::new_perlin(CHUNK_DIM, &mut seed, SET_SEED, GEN_RANGE);
-        let mut screen= drawing::Screen::new(SCREEN_DIM.0, SCREEN_DIM.1);
+        let mut world = gen::World::new(&mut seed, true, config.gen_range, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Perlin, None).unwrap();
+        world.update_chunks((0,0), config.chunk_dim);
+        let mut screen= drawing::Screen::new(config.screen_dim.0, config.screen_dim.1);
         let mut player = player::Player::spawn((0,0), sprite::Spritesheet::load("sprites/america.gif", 500).unwrap());
-        let camera_coords: (isize, isize) = (0-(SCREEN_DIM.0 as isize/2),0+(SCREEN_DIM.1 as isize/2));
+        let camera_coords: (isize, isize) = (0-(config.screen_dim.0 as isize/2),0+(config.screen_dim.1 as isize/2));
         let mouse = Mouse::new();
-        let debug_flag = false;
-        let fpslock = game::FpsLock::create_lock(TARGET_FPS);
+        let view = View {camera_coords, player_coords: player.coords, debug_fps: None};
         b.iter(||
-            draw_screen(&mut screen, &world, &mut player, camera_coords, debug_flag, fpslock.get_fps(), seed, &mouse)
+            draw_screen(&mut screen, &world, &mut player, &view, &config, &mouse)
         );
     }
 
     #[bench]
     fn bench_draw_sprite(b: &mut Bencher) {
-        let mut screen= drawing::Screen::new(SCREEN_DIM.0, SCREEN_DIM.1);
+        let config = test::black_box(config::Config::default());
+        let mut screen= drawing::Screen::new(config.screen_dim.0, config.screen_dim.1);
         let player = player::Player::spawn((0,0), sprite::Spritesheet::load("sprites/america.gif", 500).unwrap());
-        let camera_coords: (isize, isize) = (0-(SCREEN_DIM.0 as isize/2),0+(SCREEN_DIM.1 as isize/2));
+        let camera_coords: (isize, isize) = (0-(config.screen_dim.0 as isize/2),0+(config.screen_dim.1 as isize/2));
         b.iter(||
             screen.draw_sprite(&player.sprite.get_sprite(), screen.get_coords(player.coords, camera_coords))
         );
@@ -223,7 +708,8 @@ mod tests {
 
     #[bench]
     fn bench_draw_text(b: &mut Bencher) {
-        let mut screen= drawing::Screen::new(SCREEN_DIM.0, SCREEN_DIM.1);
+        let config = test::black_box(config::Config::default());
+        let mut screen= drawing::Screen::new(config.screen_dim.0, config.screen_dim.1);
         b.iter(||
             screen.draw_text((0,0), "Hello World", 32.0, &[0;4], drawing::DEBUG_FONT)
         );
@@ -231,33 +717,588 @@ mod tests {
 
     #[bench]
     fn bench_get_screen(b: &mut Bencher) {
+        let config = test::black_box(config::Config::default());                                          //hidden from the optimizer like a config file is
         let mut seed = 0;
-        let world = gen::World::new_perlin(CHUNK_DIM, &mut seed, SET_SEED, GEN_RANGE);
-        let mut screen= drawing::Screen::new(SCREEN_DIM.0, SCREEN_DIM.1);
-        let camera_coords: (isize, isize) = (0-(SCREEN_DIM.0 as isize/2),0+(SCREEN_DIM.1 as isize/2));
+        let mut world = gen::World::new(&mut seed, true, config.gen_range, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Perlin, None).unwrap();
+        world.update_chunks((0,0), config.chunk_dim);
+        let mut screen= drawing::Screen::new(config.screen_dim.0, config.screen_dim.1);
+        let camera_coords: (isize, isize) = (0-(config.screen_dim.0 as isize/2),0+(config.screen_dim.1 as isize/2));
         b.iter(||
-            world.get_screen(&mut screen.buf, camera_coords, SCREEN_DIM, CHUNK_DIM)
+            world.get_screen(&mut screen.buf, camera_coords, config.screen_dim, config.chunk_dim)
         );
     }
 
     #[bench]
     fn bench_update_location(b: &mut Bencher) {
+        let config = test::black_box(config::Config::default());
         let mut seed = 0;
-        let world = gen::World::new_perlin(CHUNK_DIM, &mut seed, SET_SEED, GEN_RANGE);
+        let mut world = gen::World::new(&mut seed, true, config.gen_range, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Perlin, None).unwrap();
+        world.update_chunks((0,0), config.chunk_dim);
         let mut player = player::Player::spawn((0,0), sprite::Spritesheet::load("sprites/america.gif", 500).unwrap());
         b.iter(||{
             player.walk(player::Direction::Right);
-            player.update_location(&world, CHUNK_DIM);                                                        
+            player.update_location(&world, config.chunk_dim);                                                        
         });
     }
 
+    #[bench]
+    fn bench_aabb_overlaps(b: &mut Bencher) {
+        let config = test::black_box(config::Config::default());
+        let mut seed = 0;
+        let mut world = gen::World::new(&mut seed, true, config.gen_range, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Perlin, None).unwrap();
+        world.update_chunks((0,0), config.chunk_dim);
+        let player = player::Player::spawn(world.spawn_point(), sprite::Spritesheet::load("sprites/america.gif", 500).unwrap());
+        let (min, max) = player.bounds();
+        b.iter(||
+            world.aabb_overlaps(min, max, config.chunk_dim)
+        );
+    }
+
+    #[bench]
+    fn bench_sweep_aabb(b: &mut Bencher) {
+        let config = test::black_box(config::Config::default());
+        let mut seed = 0;
+        let mut world = gen::World::new(&mut seed, true, config.gen_range, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Perlin, None).unwrap();
+        world.update_chunks((0,0), config.chunk_dim);
+        let player = player::Player::spawn(world.spawn_point(), sprite::Spritesheet::load("sprites/america.gif", 500).unwrap());
+        let (min, max) = player.bounds();
+        b.iter(||
+            world.sweep_aabb((min.0, min.1 + 200), (max.0, max.1 + 200), (40, -400), config.chunk_dim)         //fall onto the ground
+        );
+    }
+
+    #[bench]
+    fn bench_raycast(b: &mut Bencher) {
+        let config = test::black_box(config::Config::default());
+        let mut seed = 0;
+        let mut world = gen::World::new(&mut seed, true, config.gen_range, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Perlin, None).unwrap();
+        world.update_chunks((0,0), config.chunk_dim);
+        let spawn = world.spawn_point();
+        b.iter(||
+            world.raycast(spawn, (spawn.0 + 300, spawn.1 - 500), config.chunk_dim)
+        );
+    }
+
+    #[bench]
+    fn bench_simulate(b: &mut Bencher) {
+        let config = test::black_box(config::Config::default());
+        let mut seed = 0;
+        let mut world = gen::World::new(&mut seed, true, config.gen_range, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Perlin, None).unwrap();
+        world.update_chunks((0,0), config.chunk_dim);
+        b.iter(||{
+            world.data.values_mut().for_each(|c| c.active = true);
+            world.simulate(config.chunk_dim);
+        });
+    }
+
+    #[test]
+    fn test_registry_checks() {
+        let air = "[[material]]\nid = 0\nname = \"air\"\ncolor = [0, 0, 0, 0]\nstate = \"gas\"\ndensity = 0\ncollision = false\n";
+        let with = |m: &str| format!("{}[[material]]\nid = 1\nname = \"test\"\ncolor = [0, 0, 0, 255]\ndensity = 1000\ncollision = true\n{}", air, m);
+        assert!(material::Registry::parse(&with("state = \"liquid\"\nflow = [2, 1]")).is_ok());
+        assert!(material::Registry::parse(&with("state = \"liquid\"")).err().unwrap().to_string().contains("flow"));        //would divide by zero every tick
+        assert!(material::Registry::parse(&with("state = \"powder\"\nslide = [2, 1]")).is_ok());
+        assert!(material::Registry::parse(&with("state = \"powder\"")).err().unwrap().to_string().contains("slide"));       //would never settle
+        assert!(material::Registry::load(MATERIAL_FILE).is_ok());
+        assert!(generator::GeneratorKind::Flat.build(0, &material::Registry::parse(air).unwrap()).err().unwrap().contains("ground"));   //generators need their materials
+        let no_gems = std::fs::read_to_string(MATERIAL_FILE).unwrap().replace("name = \"gems\"", "name = \"jewels\"");
+        assert!(generator::GeneratorKind::Perlin.build(0, &material::Registry::parse(&no_gems).unwrap()).err().unwrap().contains("gems"));
+    }
+
+    #[test]
+    fn test_collision() {
+        let config = config::Config::default();
+        let w = config.chunk_dim.0 as isize;                                                                  //chunks are square
+        let mut seed = 0;
+        let mut world = gen::World::new(&mut seed, true, 1, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Empty, None).unwrap();
+        world.update_chunks((0,0), config.chunk_dim);
+        let stone = world.registry.id("stone").unwrap();
+        let solid = [(-1, -1), (w - 1, -10), (w, -20), (63, -30), (64, -40), (-65, w), (-10, -60)];
+        for coords in solid.iter() {world.set_particle(*coords, gen::Particle::new(stone, *coords), config.chunk_dim)}
+        let overlaps = |min, max| world.aabb_overlaps(min, max, config.chunk_dim);
+
+        assert!(world.is_solid((-1, -1), config.chunk_dim) && !world.is_solid((0, -1), config.chunk_dim) && !world.is_solid((-1, 0), config.chunk_dim));
+        assert!(world.is_solid((0, 10 * w), config.chunk_dim));                                                 //unloaded counts as solid
+        assert!(overlaps((-5, -5), (-1, -1)) && !overlaps((0, -5), (5, 0)) && !overlaps((-5, 0), (5, 5)));    //negative coords are the chunk to the left and below
+        assert!(!overlaps((w - 20, -15), (w - 2, -5)) && overlaps((w - 20, -15), (w - 1, -5)));               //last column of a chunk
+        assert!(!overlaps((w - 5, -25), (w - 1, -15)) && overlaps((w - 5, -25), (w + 5, -15)));               //first column of the next
+        assert!(!overlaps((0, -35), (62, -25)) && overlaps((0, -35), (63, -25)));                             //last bit of a word
+        assert!(!overlaps((65, -45), (127, -35)) && overlaps((64, -45), (127, -35)) && overlaps((60, -45), (70, -35)));   //first bit of the next
+        assert!(overlaps((-70, w - 5), (-60, w)) && !overlaps((-70, w - 5), (-60, w - 1)));                   //top row of a chunk
+        assert!(overlaps((-60, w + 1), (-50, w + 1)));                                                          //and unloaded ones above it
+
+        let sweep = |min, max, delta| world.sweep_aabb(min, max, delta, config.chunk_dim);
+        assert_eq!(sweep((-40, -65), (-30, -55), (50, 0)), collision::Sweep {travelled: (19, 0), normal: Some((-1, 0))});
+        assert_eq!(sweep((-40, -65), (-30, -55), (50, -2)), collision::Sweep {travelled: (19, 0), normal: Some((-1, 0))});   //diagonal hits the side before dropping past it
+        assert_eq!(sweep((-14, -50), (-6, -40), (0, -30)), collision::Sweep {travelled: (0, -9), normal: Some((0, 1))});   //lands on it
+        assert_eq!(sweep((w - 30, -25), (w - 20, -15), (40, 0)), collision::Sweep {travelled: (19, 0), normal: Some((-1, 0))});   //across the chunk edge
+        assert_eq!(sweep((0, -200), (10, -190), (-100, 0)), collision::Sweep {travelled: (-100, 0), normal: None});
+
+        assert_eq!(world.raycast((-50, -60), (50, -60), config.chunk_dim), Some((-10, -60)));
+        assert_eq!(world.raycast((50, -60), (-5, -60), config.chunk_dim), None);
+        assert_eq!(world.raycast((66, -42), (60, -36), config.chunk_dim), Some((64, -40)));                    //diagonal, every pixel on the line is tested
+    }
+
+    #[test]
+    fn test_heightmap() {
+        let config = config::Config::default();
+        let path = std::env::temp_dir().join("untitled_game_heightmap.png");
+        image::save_buffer(&path, &[255, 0, 128, 255, 255, 0, 127, 0], 4, 2, image::ColorType::L8).unwrap();   //white, black, middle grey and a column averaging to it
+        let kind = generator::GeneratorKind::Heightmap(path.to_str().unwrap().to_string());
+        let mut seed = 0;
+        let mut world = gen::World::new(&mut seed, true, 1, material::Registry::load(MATERIAL_FILE).unwrap(), kind, None).unwrap();
+        world.update_chunks((0,0), config.chunk_dim);
+        let solid = |coords| world.get_particle(coords, config.chunk_dim).unwrap().material() != material::AIR;
+        assert!(solid((0, 0)) && !solid((0, 1)));                                                               //white is the top of the range
+        for &x in &[2, 3, -2, 6] {assert!(solid((x, -258)) && !solid((x, -257)), "column {}", x)}              //rows are averaged and it repeats sideways
+        assert_eq!(world.spawn_point(), (0, 65));                                                                //a players height over the first open cell at x 0
+    }
+
+    ///an empty world with a stone floor under y -99 and walls either side of x -half..half, for watching particles settle
+    #[allow(dead_code)]
+    fn tank(half: isize) -> gen::World {
+        let chunk_dim = config::Config::default().chunk_dim;
+        let mut seed = 0;
+        let mut world = gen::World::new(&mut seed, true, 1, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Empty, None).unwrap();
+        world.update_chunks((0,0), chunk_dim);
+        fill(&mut world, "stone", -half-1..half+1, -100..-99);
+        fill(&mut world, "stone", -half-1..-half, -99..-20);
+        fill(&mut world, "stone", half..half+1, -99..-20);
+        world
+    }
+
+    ///fills a rectangle of the world with a material
+    #[allow(dead_code)]
+    fn fill(world: &mut gen::World, name: &str, xs: std::ops::Range<isize>, ys: std::ops::Range<isize>) {
+        let chunk_dim = config::Config::default().chunk_dim;
+        let id = world.registry.id(name).unwrap();
+        for x in xs {for y in ys.clone() {world.set_particle((x, y), gen::Particle::new(id, (x, y)), chunk_dim)}}
+    }
+
+    ///materials in a column of a tank, bottom up
+    #[allow(dead_code)]
+    fn column(world: &gen::World, x: isize) -> Vec<material::MaterialId> {
+        (-99..-20).map(|y| world.get_particle((x, y), config::Config::default().chunk_dim).unwrap().material()).collect()
+    }
+
+    #[test]
+    fn test_powder() {
+        let chunk_dim = config::Config::default().chunk_dim;
+        for name in ["sand", "gravel"].iter() {
+            let mut world = tank(60);
+            fill(&mut world, name, 0..1, -99..-40);
+            for _ in 0..1000 {world.simulate(chunk_dim)}
+            let (run, drop) = world.registry.get(world.registry.id(name).unwrap()).slide;
+            let heights: Vec<isize> = (-60..60).map(|x| column(&world, x).iter().filter(|m| **m != material::AIR).count() as isize).collect();
+            let steepest = heights.windows(run as usize + 1).map(|w| (w[0] - w[run as usize]).abs()).max();
+            assert_eq!(steepest, Some(drop), "{} piles at its angle of repose", name);                      //never steeper than its slide and no flatter
+        }
+
+        let mut world = tank(20);
+        fill(&mut world, "water", -20..20, -99..-69);
+        fill(&mut world, "sand", -5..5, -60..-50);
+        for _ in 0..1000 {world.simulate(chunk_dim)}
+        let (sand, water) = (world.registry.id("sand").unwrap(), world.registry.id("water").unwrap());
+        assert_eq!(column(&world, 0)[0], sand);                                                             //sank to the floor
+        assert!((-20..20).all(|x| column(&world, x).iter().skip_while(|m| **m == sand).all(|m| *m != sand)));   //with all the water above it
+        assert_eq!((-20..20).map(|x| column(&world, x).iter().filter(|m| **m == water).count()).sum::<usize>(), 40 * 30);
+    }
+
+    #[test]
+    fn test_liquids() {
+        let chunk_dim = config::Config::default().chunk_dim;
+        let mut world = tank(40);
+        fill(&mut world, "oil", -40..-30, -99..-79);
+        fill(&mut world, "water", -40..-30, -79..-49);
+        for _ in 0..3000 {world.simulate(chunk_dim)}
+        let (oil, water) = (world.registry.id("oil").unwrap(), world.registry.id("water").unwrap());
+        assert!((-40..40).all(|x| column(&world, x).iter().skip_while(|m| **m != oil).all(|m| *m != water)));   //oil floats, not wedged beside the water
+        assert!(column(&world, 39)[0] == water && column(&world, -40).contains(&oil));
+
+        let mut world = tank(30);                                                                           //narrower than the range liquids look for somewhere lower
+        fill(&mut world, "water", -30..-20, -99..-39);
+        for _ in 0..2000 {world.simulate(chunk_dim)}
+        let heights: Vec<usize> = (-30..30).map(|x| column(&world, x).iter().filter(|m| **m == water).count()).collect();
+        assert!(heights.iter().max().unwrap() - heights.iter().min().unwrap() <= 1);                         //pressure spreads it level
+    }
+
+    ///a player standing on the floor of a tank with the middle of its feet at x
+    #[allow(dead_code)]
+    fn stand(world: &gen::World, x: isize) -> player::Player {
+        let mut player = player::Player::spawn((0,0), sprite::Spritesheet::load("sprites/america.gif", 500).unwrap());
+        player.respawn(player.standing_on((x, -99)));
+        player.update_location(world, config::Config::default().chunk_dim);
+        player
+    }
+
+    #[test]
+    fn test_jumping() {
+        let chunk_dim = config::Config::default().chunk_dim;
+        let world = tank(200);
+        let mut player = stand(&world, 0);
+        let ground = player.coords;
+        for _ in 0..60 {player.update_location(&world, chunk_dim)}
+        assert!(player.on_ground && player.coords == ground);
+        player.jump();
+        player.update_location(&world, chunk_dim);
+        assert!(!player.on_ground && player.coords.1 > ground.1);
+        while player.velocity.1 > 0.0 {player.update_location(&world, chunk_dim)}
+        player.jump();                                                                                      //at the top of the jump
+        for _ in 0..6 {
+            player.update_location(&world, chunk_dim);
+            assert!(player.velocity.1 <= 0.0);                                                              //cant jump off thin air
+        }
+        for _ in 0..60 {player.update_location(&world, chunk_dim)}
+        assert!(player.on_ground && player.coords == ground);                                              //and the press ran out before landing
+
+        player.noclip = true;
+        for _ in 0..30 {
+            player.walk(player::Direction::Down);
+            player.update_location(&world, chunk_dim);
+        }
+        assert!(player.coords.1 < ground.1 - 50 && !player.on_ground);                                     //flew down through the floor
+    }
+
+    #[test]
+    fn test_stepping() {
+        let chunk_dim = config::Config::default().chunk_dim;
+        let walk = |world: &gen::World, player: &mut player::Player, ticks| for _ in 0..ticks {
+            player.walk(player::Direction::Right);
+            player.update_location(world, chunk_dim);
+        };
+        let step_height = stand(&tank(200), 0).step_height;
+        for h in 1..step_height+1 {
+            let mut world = tank(200);
+            fill(&mut world, "stone", 60..190, -99..-99+h);
+            let mut player = stand(&world, 0);
+            walk(&world, &mut player, 60);
+            assert_eq!(player.bounds().0.1, -99 + h, "steps up a {}px ledge", h);
+            assert!(player.bounds().0.0 > 60 && player.on_ground);
+        }
+
+        let mut world = tank(200);
+        let mut player = stand(&world, 0);
+        fill(&mut world, "stone", 60..70, -99..-99+step_height+1);
+        walk(&world, &mut player, 60);
+        assert_eq!((player.bounds().1.0, player.bounds().0.1), (59, -99));                                 //stopped by a taller wall
+        player.jump();
+        walk(&world, &mut player, 3);
+        assert!(player.bounds().1.0 == 59 && player.bounds().0.1 > -99);                                   //pushing into it while jumping still rises
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let config = config::Config::default();
+        let dir = std::env::temp_dir().join("untitled_game_round_trip");
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.to_str().unwrap();
+        let mut seed = 0;
+        let mut world = gen::World::new(&mut seed, true, 1, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Perlin, Some(save::SaveDir::open(path).unwrap())).unwrap();
+        world.update_chunks((0,0), config.chunk_dim);
+        assert!(!dir.join("world.toml").exists());                                                              //loading untouched chunks writes nothing
+        let sand = world.registry.id("sand").unwrap();
+        let (chunk, (ix, iy)) = gen::World::get_chunk_coords((10, -5), config.chunk_dim);
+        assert_eq!(world.get_particle((10, -5), config.chunk_dim).unwrap().material(), material::AIR);
+        world.data.get_mut(&chunk).unwrap().data[iy*config.chunk_dim.0+ix] = gen::Particle::new(sand, (10, -5));   //as if it generated there
+        world.wake((10, -5), config.chunk_dim);
+        for _ in 0..20 {world.simulate(config.chunk_dim)}
+        assert_eq!(world.get_particle((10, -5), config.chunk_dim).unwrap().material(), material::AIR);
+        assert!(world.data.values().all(|c| !c.modified));                                                      //generated particles settling isnt an edit
+        for x in -300..300 {                                                                                    //edit across chunk and region borders
+            world.set_particle((x, -10), gen::Particle::new(sand, (x, -10)), config.chunk_dim);
+        }
+        world.data.get_mut(&(0,0)).unwrap().state_mut(5).lifetime = 7;
+        world.data.get_mut(&(0,0)).unwrap().modified = true;
+        for _ in 0..20 {world.simulate(config.chunk_dim)}
+        world.save(config.chunk_dim).unwrap();
+
+        let mut seed = 0;
+        let mut loaded = gen::World::new(&mut seed, false, 1, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Flat, Some(save::SaveDir::open(path).unwrap())).unwrap();
+        loaded.update_chunks((0,0), config.chunk_dim);
+        assert_eq!(loaded.seed, world.seed);
+        assert_eq!(loaded.generator_kind, world.generator_kind);                                                //saved generator wins over the one asked for
+
+        let mut threaded = gen::World::new(&mut seed, false, 1, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Flat, Some(save::SaveDir::open(path).unwrap())).unwrap();
+        threaded.start_workers(2, config.chunk_dim);                                                           //workers load saved chunks too
+        for _ in 0..5000 {
+            threaded.update_chunks((0,0), config.chunk_dim);
+            if threaded.data.len() == world.data.len() {break}
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        for loaded in &[loaded, threaded] {
+            assert_eq!(loaded.data.len(), world.data.len());
+            for (coords, chunk) in &world.data {
+                let other = &loaded.data[coords];
+                assert!(chunk.data == other.data, "particles differ in chunk {:?}", coords);
+                assert!(chunk.state == other.state, "state differs in chunk {:?}", coords);
+            }
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_save_region_cache() {
+        let config = config::Config::default();
+        let dir = std::env::temp_dir().join("untitled_game_region_cache");
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.to_str().unwrap();
+        let mut seed = 0;
+        let mut world = gen::World::new(&mut seed, false, 1, material::Registry::load(MATERIAL_FILE).unwrap(), generator::GeneratorKind::Empty, Some(save::SaveDir::open(path).unwrap())).unwrap();
+        world.update_chunks((0,0), config.chunk_dim);
+        let stone = world.registry.id("stone").unwrap();
+        world.set_particle((5, -5), gen::Particle::new(stone, (5, -5)), config.chunk_dim);
+        world.update_chunks((save::REGION_DIM * 4 * config.chunk_dim.0 as isize, 0), config.chunk_dim);       //walk far enough to leave the region behind
+        assert!(world.save.as_ref().unwrap().load_chunk((0,0), config.chunk_dim).unwrap().is_some());           //written on the way out
+        world.update_chunks((save::REGION_DIM * 8 * config.chunk_dim.0 as isize, 0), config.chunk_dim);
+        std::fs::remove_file(dir.join("r.0.0.region")).unwrap();
+        assert!(world.save.as_ref().unwrap().load_chunk((0,0), config.chunk_dim).unwrap().is_none());           //and not kept in memory once nothing near it is loaded
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_save_unknown_material() {
+        let config = config::Config::default();
+        let dir = std::env::temp_dir().join("untitled_game_unknown_material");
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.to_str().unwrap();
+        let registry = material::Registry::load(MATERIAL_FILE).unwrap();
+        let mut data = vec![gen::Particle(0); config.chunk_dim.0*config.chunk_dim.1];
+        data[5] = gen::Particle::new(registry.count() as material::MaterialId, (5, 0));                           //saved before materials.toml lost one
+        let mut save = save::SaveDir::open(path).unwrap();
+        save.store_chunk(gen::Chunk::new((0,0), data), config.chunk_dim).unwrap();
+        save.flush(0, &generator::GeneratorKind::Empty, config.chunk_dim).unwrap();
+
+        let mut seed = 0;
+        let mut world = gen::World::new(&mut seed, false, 1, registry, generator::GeneratorKind::Empty, Some(save::SaveDir::open(path).unwrap())).unwrap();
+        assert!(matches!(world.save.as_ref().unwrap().load_chunk((0,0), config.chunk_dim), Err(save::SaveError::Format(_))));
+        world.update_chunks((0,0), config.chunk_dim);                                                          //generates it instead of panicking drawing it
+        assert!(world.data[&(0,0)].data.iter().all(|p| p.material() == material::AIR));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_replay_smoke() {
+        let replay = script::Replay::load("replays/smoke.replay").unwrap();
+        let mut session = Session::new(replay.seed, replay.generator.parse().unwrap(), &replay.config(&config::Config::default()).unwrap()).unwrap();
+        session.start_recording();
+        for t in 0..replay.script.ticks() {
+            session.step(replay.script.get(t));
+        }
+        assert_eq!(session.player.coords, (570, -65));                                                      //walks over the hill and lands by the crater
+        assert_eq!(session.replay().unwrap().script.to_string(), replay.script.to_string());                         //recording it again gives the same replay
+    }
+
+    #[test]
+    fn test_replay_settings() {
+        for &debug in &[true, false] {
+            let config = config::Config {screen_dim: (1280, 720), chunk_dim: (64, 64), gen_range: 2, debug, ..Default::default()};
+            let mut session = Session::new(3, generator::GeneratorKind::Perlin, &config).unwrap();
+            session.start_recording();
+            for t in 0..120 {session.step(script::Controls {right: t < 60, up: t < 60, noclip: t == 10, dig: t >= 60, view_distance: if t == 90 {3} else {0}, cursor: (640, 500), ..Default::default()})}
+            assert_eq!(session.player.noclip, debug);
+            let replay = script::Replay::parse(&session.replay().unwrap().to_string()).unwrap();
+            assert_eq!((replay.screen_dim, replay.chunk_dim, replay.gen_range, replay.debug), ((1280, 720), (64, 64), 2, debug));
+
+            let mut played = Session::new(replay.seed, replay.generator.parse().unwrap(), &replay.config(&config::Config {debug: !debug, ..Default::default()}).unwrap()).unwrap();
+            for t in 0..replay.script.ticks() {played.step(replay.script.get(t))}
+            assert_eq!((played.player.coords, played.world.gen_range), (session.player.coords, 3));         //settings changed while playing come back too
+            assert_eq!(played.world.data_hash(), session.world.data_hash());                                //same world even though the defaults differ
+        }
+    }
+
+    #[test]
+    fn test_config_overrides() {
+        let mut config = config::Config::parse("resolution = [1280, 720]\nchunk_size = 64\nseed = \"hello\"").unwrap();
+        assert_eq!((config.screen_dim, config.chunk_dim, config.gen_range), ((1280, 720), (64, 64), 4));       //left out settings keep defaults
+        assert_eq!(config.seed, Some(config::parse_seed("hello")));
+        let args: Vec<String> = ["--windowed", "--seed", "42", "--chunk-size", "128"].iter().map(|a| a.to_string()).collect();
+        config.apply_args(&args).unwrap();
+        assert_eq!((config.fullscreen, config.seed, config.chunk_dim), (false, Some(42), (128, 128)));
+        assert!(config::Config::parse("chunk_size = 300").unwrap_err().contains("power of two"));
+        assert!(config.apply_args(&["--view-distance".to_string(), "0".to_string()]).is_err());
+        for coords in [(-1, 1), (-64, 64), (63, -63), (-65, 0), (1000, -1000)].iter() {                       //shift and mask split like div_euclid
+            let ((cx, cy), (ix, iy)) = gen::World::get_chunk_coords(*coords, (64, 64));
+            assert_eq!((cx*64 + ix as isize, cy*64 - iy as isize), *coords);
+            assert!(ix < 64 && iy < 64);
+            assert_eq!(gen::World::get_world_coords((cx, cy), iy*64 + ix, (64, 64)), *coords);
+        }
+    }
+
+    #[test]
+    fn test_bindings_parse() {
+        use bindings::{Action, Bindings, Input};
+        let parsed = Bindings::parse("move_left = [\"A\", \"Left\"]\ndig = [\"MouseLeft\", \"MouseRight\"]\nslot_3 = [\"Key3\"]\njump = []").unwrap();
+        assert_eq!(parsed.get(Action::MoveLeft), &[Input::Key(game::VirtualKeyCode::A), Input::Key(game::VirtualKeyCode::Left)]);   //any number of bindings each
+        assert_eq!(parsed.get(Action::Dig), &[Input::Mouse(0), Input::Mouse(1)]);
+        assert_eq!(parsed.get(Action::Slot(3)), &[Input::Key(game::VirtualKeyCode::Key3)]);
+        assert!(parsed.get(Action::Jump).is_empty() && parsed.get(Action::Sprint).is_empty());                  //empty or left out is unbound
+        assert!(Bindings::parse("dance = [\"A\"]").err().unwrap().contains("unknown action dance"));
+        assert!(Bindings::parse("jump = [\"Spacebar\"]").err().unwrap().contains("unknown key Spacebar"));
+        assert!(Bindings::parse("jump = \"Space\"").is_err());                                                //has to be a list
+
+        let mut rebound = Bindings::defaults();
+        rebound.bind(Action::Jump, Input::Mouse(2));
+        rebound.clear(Action::Dig);
+        let written = Bindings::parse(&rebound.to_string()).unwrap();                                           //what rebinding in game writes back
+        assert!(Action::all().all(|a| written.get(a) == rebound.get(a)));
+        assert_eq!(written.get(Action::Jump), &[Input::Key(game::VirtualKeyCode::Space), Input::Mouse(2)]);
+    }
+
+    #[test]
+    fn test_menu_states() {
+        let mut config = config::Config::default();
+        let press = |f: fn(&mut state::MenuInput)| {let mut input = state::MenuInput::default(); f(&mut input); input};
+        let (back, down, right, confirm) = (press(|i| i.back = true), press(|i| i.down = true), press(|i| i.right = true), press(|i| i.confirm = true));
+
+        let mut paused = match state::State::Playing.update(&back, &mut config) {                          //escape pauses instead of quitting
+            state::Transition::Push(s) => s,
+            t => panic!("playing went to {:?}", t),
+        };
+        assert_eq!(paused.update(&back, &mut config), state::Transition::Pop);
+        paused.update(&down, &mut config);
+        paused.update(&down, &mut config);
+        assert_eq!(paused.update(&confirm, &mut config), state::Transition::ToMenu);
+
+        let mut new_world = state::State::NewWorld(String::new());
+        new_world.update(&press(|i| i.typed = "hello".to_string()), &mut config);
+        assert_eq!(new_world.update(&confirm, &mut config), state::Transition::Create(Some(config::parse_seed("hello"))));
+
+        let mut settings = state::State::Settings(state::Menu::default());
+        settings.update(&down, &mut config);
+        settings.update(&right, &mut config);
+        assert_eq!(config.gen_range, 5);                                                                    //view distance went up
+        settings.update(&down, &mut config);
+        settings.update(&down, &mut config);
+        let mut controls = match settings.update(&confirm, &mut config) {
+            state::Transition::Push(s) => s,
+            t => panic!("controls went to {:?}", t),
+        };
+        let key = press(|i| i.pressed = Some(bindings::Input::Key(game::VirtualKeyCode::J)));
+        assert_eq!(controls.update(&key, &mut config), state::Transition::None);                           //nothing binds until an action is picked
+        controls.update(&confirm, &mut config);
+        assert_eq!(controls.update(&key, &mut config), state::Transition::Bind(bindings::Action::MoveLeft, bindings::Input::Key(game::VirtualKeyCode::J)));
+        controls.update(&confirm, &mut config);
+        assert_eq!(controls.update(&back, &mut config), state::Transition::None);                          //cancels waiting without leaving
+        assert_eq!(controls.update(&press(|i| i.erase = true), &mut config), state::Transition::Unbind(bindings::Action::MoveLeft));
+        assert_eq!(controls.update(&back, &mut config), state::Transition::Pop);
+
+        let mut states = vec![state::State::MainMenu(state::Menu::default()), state::State::Playing, state::State::Inventory(3, Some(1))];
+        game_over(&mut states, "Fell too far".to_string());                                              //dying with the inventory open
+        assert_eq!(states[1..], [state::State::Playing, state::State::GameOver(state::Menu::default(), "Fell too far".to_string())]);
+
+        let old = bindings::Bindings::parse("quit = [\"Escape\"]\nnext_material = [\"E\"]").unwrap();       //controls files from before actions were renamed still load
+        assert_eq!(old.get(bindings::Action::Pause), bindings::Bindings::defaults().get(bindings::Action::Pause));
+        assert_eq!(old.get(bindings::Action::NextSlot), bindings::Bindings::defaults().get(bindings::Action::NextSlot));
+    }
+
+    ///a session on flat ground, stepped until the player has landed at spawn. the camera is still easing after
+    #[allow(dead_code)]
+    fn landed() -> Session {
+        let mut session = Session::new(0, generator::GeneratorKind::Flat, &config::Config::default()).unwrap();
+        for _ in 0..60 {session.step(script::Controls::default())}
+        session
+    }
+
+    ///world coords in the ground off to the side of the player, and the cursor over them
+    #[allow(dead_code)]
+    fn beside(session: &Session) -> ((isize, isize), (isize, isize)) {
+        let (min, _) = session.player.bounds();
+        let below = (min.0 - 40, min.1 - 20);
+        (below, (below.0 - session.camera_coords.0, session.camera_coords.1 - below.1))
+    }
+
+    #[test]
+    fn test_damage() {
+        let mut session = landed();
+        let chunk_dim = session.config.chunk_dim;
+        let ground = session.player.coords;
+        session.step(script::Controls {jump: true, ..Default::default()});
+        for _ in 0..60 {session.step(script::Controls::default())}
+        assert_eq!(session.player.health, player::MAX_HEALTH);                                              //jumping doesnt hurt
+
+        session.player.respawn((ground.0, ground.1 + 400));
+        for _ in 0..120 {session.step(script::Controls::default())}
+        assert!(session.player.health < player::MAX_HEALTH && session.player.last_damage == Some(player::Damage::Fall));
+
+        let (min, max) = session.player.bounds();
+        let lava = session.world.registry.id("lava").unwrap();
+        for x in min.0..max.0+1 {session.world.set_particle((x, min.1), gen::Particle::new(lava, (x, min.1)), chunk_dim)}
+        for _ in 0..300 {session.step(script::Controls::default())}
+        assert_eq!((session.player.health, session.player.last_damage), (0, Some(player::Damage::Hazard(lava))));
+
+        let (dead_at, tick) = (session.player.coords, session.world.tick);
+        for _ in 0..30 {session.step(script::Controls {right: true, ..Default::default()})}
+        assert_eq!((session.player.coords, session.world.tick), (dead_at, tick));                        //nothing moves until respawning, same as the death screen
+        session.step(script::Controls {respawn: true, ..Default::default()});
+        assert_eq!((session.player.health, session.player.last_damage), (player::MAX_HEALTH, None));
+    }
+
+    #[test]
+    fn test_inventory() {
+        let mut session = landed();
+        let (below, cursor) = beside(&session);
+        let ground = inventory::Item::Material(session.world.get_particle(below, session.config.chunk_dim).unwrap().material());
+
+        for _ in 0..30 {session.step(script::Controls {dig: true, cursor, ..Default::default()})}
+        let dug = session.player.inventory.count(ground);
+        assert!(dug > 0);
+
+        session.step(script::Controls {slot: 6, cursor, ..Default::default()});                         //dug particles went in after the starting items
+        assert_eq!(session.player.inventory.held().map(|s| s.item), Some(ground));
+        session.step(script::Controls {place: true, cursor, ..Default::default()});
+        assert!(session.player.inventory.count(ground) < dug);
+
+        session.step(script::Controls {scroll: -2, cursor, ..Default::default()});
+        assert_eq!(session.player.inventory.held().map(|s| s.item), Some(inventory::Item::Bomb));
+        session.step(script::Controls {explode: true, cursor, ..Default::default()});
+        assert_eq!(session.player.inventory.count(inventory::Item::Bomb), 7);
+        let (min, max) = session.player.bounds();
+        let feet = ((min.0 + max.0)/2 - session.camera_coords.0, session.camera_coords.1 - min.1);
+        session.step(script::Controls {cursor: feet, ..Default::default()});
+        session.step(script::Controls {explode: true, cursor: feet, ..Default::default()});
+        assert_eq!(session.player.last_damage, Some(player::Damage::Explosion));                          //bombs blast whoever is standing on them
+
+        let shovel = session.player.inventory.slots[0];
+        let script = script::Script::parse("1 swap=0,20 cursor=0,0").unwrap();                            //moves from the inventory screen play back from scripts
+        session.step(script.get(0));
+        assert_eq!((session.player.inventory.slots[0], session.player.inventory.slots[20]), (None, shovel));
+        assert!(script::Script::parse("1 swap=0,36").is_err());
+    }
+
+    #[test]
+    fn test_tools() {
+        let mut session = landed();
+        let chunk_dim = session.config.chunk_dim;
+        let (below, cursor) = beside(&session);
+        let (gems, bedrock) = (session.world.registry.id("gems").unwrap(), session.world.registry.id("bedrock").unwrap());
+        for y in below.1-32..below.1+33 {
+            for x in below.0-32..below.0+33 {session.world.set_particle((x, y), gen::Particle::new(gems, (x, y)), chunk_dim)}
+        }
+        session.world.set_particle(below, gen::Particle::new(bedrock, below), chunk_dim);               //nothing digs bedrock
+
+        session.step(script::Controls {slot: 2, cursor, ..Default::default()});                         //pickaxe isnt enough for gems
+        for _ in 0..60 {session.step(script::Controls {dig: true, cursor, ..Default::default()})}
+        assert_eq!(session.player.inventory.count(inventory::Item::Material(gems)), 0);
+        assert_eq!(session.player.inventory.held().map(|s| s.item), Some(inventory::Item::Tool(tool::Tool::Pickaxe, tool::Tool::Pickaxe.durability())));
+
+        session.step(script::Controls {slot: 3, cursor, ..Default::default()});
+        for _ in 0..60 {session.step(script::Controls {dig: true, cursor, ..Default::default()})}
+        assert!(session.player.inventory.count(inventory::Item::Material(gems)) > 0);
+        assert_eq!(session.world.get_particle(below, chunk_dim).unwrap().material(), bedrock);
+        assert!(matches!(session.player.inventory.held().map(|s| s.item), Some(inventory::Item::Tool(tool::Tool::Drill, left)) if left < tool::Tool::Drill.durability()));
+        let (width, depth) = tool::Tool::Drill.brush();
+        let dug = |coords| session.world.get_particle(coords, chunk_dim).unwrap().material() == material::AIR;
+        assert!(dug((below.0, below.1 - depth + 2)) && !dug((below.0 - width - 2, below.1)));          //drills go deep, not wide
+    }
+
     #[bench]
     fn bench_update_camera(b: &mut Bencher) {
+        let config = test::black_box(config::Config::default());
         let mut player = player::Player::spawn((0,0), sprite::Spritesheet::load("sprites/america.gif", 500).unwrap());
-        let mut camera_coords: (isize, isize) = (0-(SCREEN_DIM.0 as isize/2),0+(SCREEN_DIM.1 as isize/2));
+        let mut camera_coords: (isize, isize) = (0-(config.screen_dim.0 as isize/2),0+(config.screen_dim.1 as isize/2));
         b.iter(||{
             player.walk(player::Direction::Right);
-            update_camera(&mut camera_coords, &mut player);
+            update_camera(&mut camera_coords, &mut player, config.screen_dim);
         });
     }
 }
\ No newline at end of file
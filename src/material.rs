@@ -0,0 +1,157 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+pub type MaterialId = u16;
+
+pub const AIR: MaterialId = 0;  //registry must define air first
+pub const SHADE_BITS: u16 = 3;  //low bits of a particle that hold its shade
+pub const SHADES: usize = 1 << SHADE_BITS;  //how many color variants each material gets in the palette
+pub const MAX_MATERIALS: usize = 1 << (16 - SHADE_BITS);  //ids have to fit in the rest of a particle
+
+///how a material behaves in the simulation
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    Solid,
+    Powder,
+    Liquid,
+    Gas,
+}
+
+///properties of one material as read from the registry file
+#[derive(Deserialize, Clone, Debug)]
+pub struct Material {
+    pub id: MaterialId,
+    pub name: String,
+    pub color: [u8;4],              //base rgba color
+    #[serde(default)]
+    pub color_variance: u8,         //how far shades can stray from base color
+    pub state: State,
+    pub density: u16,               //heavier particles sink through lighter liquids
+    pub collision: bool,
+    #[serde(default)]
+    pub hardness: u8,
+    #[serde(default)]
+    pub tier: u8,                   //lowest tool tier that can dig it, 255 is never
+    #[serde(default)]
+    pub slide: (isize, isize),      //powders: how far sideways and down a blocked particle slides. drop/run is its angle of repose
+    #[serde(default)]
+    pub flow: (isize, u64),         //liquids: how many px it spreads sideways per step and how many ticks between steps
+    #[serde(default)]
+    pub drag: f32,                  //liquids: fraction of velocity lost per tick while fully submerged
+    #[serde(default)]
+    pub damage: u8,                 //health lost every hurt interval the player touches it
+    #[serde(default)]
+    pub respawn: bool,              //touching it sets where the player respawns
+}
+
+impl Material {
+    ///if the material falls and piles up
+    pub fn is_powder(&self) -> bool {
+        self.state == State::Powder
+    }
+
+    ///if the material flows and levels out
+    pub fn is_liquid(&self) -> bool {
+        self.state == State::Liquid
+    }
+}
+
+#[derive(Deserialize)]
+struct RegistryFile {
+    material: Vec<Material>,
+}
+
+
+
+#[derive(Debug)]
+pub enum RegistryError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegistryError::Io(e) => write!(f, "couldn't read material file: {}", e),
+            RegistryError::Parse(e) => write!(f, "couldn't parse material file: {}", e),
+            RegistryError::Invalid(s) => write!(f, "invalid material file: {}", s),
+        }
+    }
+}
+
+
+
+///every material in the game, indexed by id
+pub struct Registry {
+    materials: Vec<Material>,
+    names: HashMap<String, MaterialId>,
+    palette: Vec<[u8;4]>,           //SHADES colors per material, built once so rendering is a lookup. indexed by packed particle value
+}
+
+impl Registry {
+    ///loads the registry from a toml file
+    pub fn load(path: &str) -> Result<Self, RegistryError> {
+        let text = std::fs::read_to_string(path).map_err(RegistryError::Io)?;
+        Registry::parse(&text)
+    }
+
+    ///builds the registry from toml text. ids have to count up from 0 with air first
+    pub fn parse(text: &str) -> Result<Self, RegistryError> {
+        let file: RegistryFile = toml::from_str(text).map_err(RegistryError::Parse)?;
+        if file.material.len() > MAX_MATERIALS {return Err(RegistryError::Invalid(format!("too many materials, max is {}", MAX_MATERIALS)))}
+        let mut names = HashMap::new();
+        for (i, m) in file.material.iter().enumerate() {
+            if m.id as usize != i {return Err(RegistryError::Invalid(format!("material {} has id {} but should be {}", m.name, m.id, i)))}
+            if names.insert(m.name.clone(), m.id).is_some() {return Err(RegistryError::Invalid(format!("material {} defined twice", m.name)))}
+            if m.is_liquid() && m.flow.1 == 0 {return Err(RegistryError::Invalid(format!("liquid {} needs a flow with at least 1 tick between steps", m.name)))}
+            if m.is_powder() && m.slide == (0, 0) {return Err(RegistryError::Invalid(format!("powder {} needs a slide", m.name)))}   //otherwise it would swap with itself forever
+        }
+        match file.material.first() {
+            Some(m) if m.name == "air" => {},
+            _ => return Err(RegistryError::Invalid("first material must be air".to_string())),
+        }
+
+        let palette = file.material.iter().flat_map(|m| (0..SHADES).map(move |shade| {
+            let offset = (shade as isize * 2 - (SHADES as isize - 1)) * m.color_variance as isize / (SHADES as isize - 1);   //spread shades evenly over -variance..variance
+            let c = |channel: u8| (channel as isize + offset).clamp(0, 255) as u8;
+            [c(m.color[0]), c(m.color[1]), c(m.color[2]), m.color[3]]
+        })).collect();
+
+        Ok(Self {
+            materials: file.material,
+            names,
+            palette,
+        })
+    }
+
+    #[inline]
+    pub fn get(&self, id: MaterialId) -> &Material {
+        &self.materials[id as usize]
+    }
+
+    ///how many materials there are, ids count up from 0 to one less than this
+    pub fn count(&self) -> usize {
+        self.materials.len()
+    }
+
+    ///gets a material id by name
+    pub fn id(&self, name: &str) -> Option<MaterialId> {
+        self.names.get(name).copied()
+    }
+
+    ///gets every rendered color, laid out so a packed particle value is its index
+    #[inline]
+    pub fn palette(&self) -> &[[u8;4]] {
+        &self.palette
+    }
+}
+
+///picks a shade for a particle from its world coords so terrain looks grainy but generates the same every time
+pub fn shade_at(coords: (isize, isize)) -> u8 {
+    let mut h = (coords.0 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ (coords.1 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    h ^= h >> 29;
+    (h % SHADES as u64) as u8
+}
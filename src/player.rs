@@ -1,5 +1,24 @@
 use engine::sprite;
 use crate::gen::*;
+use crate::inventory::Inventory;
+use crate::material::{Material, MaterialId, Registry};
+
+pub const MAX_HEALTH: usize = 100;
+const GRAVITY: f32 = 12.0;          //velocity lost per tick falling. velocity is in tenths of a px per tick
+const TERMINAL_VELOCITY: f32 = 220.0;
+const JUMP_VELOCITY: f32 = 135.0;
+const JUMP_CUT: f32 = 0.4;          //fraction of upward velocity kept when space is let go early
+const COYOTE_TICKS: u8 = 6;         //ticks after walking off a ledge the player can still jump
+const JUMP_BUFFER_TICKS: u8 = 6;    //ticks a jump pressed before landing is remembered
+const STEP_HEIGHT: isize = 5;       //default px the player walks up without jumping
+const PLAYER_DENSITY: f32 = 900.0;  //compared against liquid density for buoyancy
+const BLAST_REACH: f32 = 2.0;       //how many explosion radii away the player still gets hurt
+const BLAST_DAMAGE: f32 = 4.0;      //health lost per point of power at the center
+const BLAST_KNOCKBACK: f32 = 40.0;  //velocity gained per point of power at the center
+const SAFE_FALL_VELOCITY: f32 = 160.0;  //landing slower than this doesnt hurt, a full jump lands a bit under JUMP_VELOCITY+GRAVITY
+const FALL_DAMAGE: f32 = 1.0;       //health lost per unit of landing velocity over safe
+const SUFFOCATION_DAMAGE: usize = 10;
+const HURT_INTERVAL: u8 = 30;       //ticks between hurts from touching hazards or being buried
 
 pub enum Direction {
     Left,
@@ -8,62 +27,206 @@ pub enum Direction {
     Down,
 }
 
+///what hurt the player
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Damage {
+    Fall,
+    Hazard(MaterialId),                 //touching a material that does damage
+    Suffocation,
+    Explosion,
+}
+
+impl Damage {
+    ///how the death screen says the player died
+    pub fn describe(&self, registry: &Registry) -> String {
+        match self {
+            Damage::Fall => "Fell too far".to_string(),
+            Damage::Hazard(id) => format!("Killed by {}", registry.get(*id).name),
+            Damage::Suffocation => "Suffocated".to_string(),
+            Damage::Explosion => "Blown up".to_string(),
+        }
+    }
+}
+
 pub struct Player {
     pub health: usize,
+    pub last_damage: Option<Damage>,    //what hurt the player last, tells how they died
+    pub bed: Option<(isize, isize)>,    //world coords of the last respawn material touched
+    pub inventory: Inventory,           //kept through respawns
     pub coords: (isize, isize),
+    pub last_coords: (isize, isize),    //coords before the last tick, for drawing in between
     pub velocity: (f32, f32),
     pub max_velocity: f32,
     pub acceleration_speed: f32,
     pub deceleration_unit: f32,
     pub running: bool,
+    pub on_ground: bool,            //if something solid is right under the hitbox
+    pub noclip: bool,               //debug free flying through everything
+    pub step_height: isize,         //tallest bump walked straight over, also how far down slopes the player sticks to the ground
+    hitbox: ((isize, isize), (isize, isize)),  //corners of the box around the sprite hitbox, relative to coords
+    coyote: u8,                     //ticks left to jump after leaving the ground
+    jump_buffer: u8,                //ticks left on a jump waiting to land
+    hurt_cooldown: u8,              //ticks until hazards or being buried can hurt again
     pub sprite: sprite::Spritesheet,
 }
 
 impl Player {
     pub fn spawn(coords: (isize, isize), sprite: sprite::Spritesheet) -> Player {
+        let outline = sprite.get_hitbox((0,0));
+        let min = outline.iter().fold((isize::MAX, isize::MAX), |m, c| (m.0.min(c.0), m.1.min(c.1)));
+        let max = outline.iter().fold((isize::MIN, isize::MIN), |m, c| (m.0.max(c.0), m.1.max(c.1)));
         Player{
-            health: 100,
+            health: MAX_HEALTH,
+            last_damage: None,
+            bed: None,
+            inventory: Inventory::starting(),
             coords,
+            last_coords: coords,
             velocity: (0.0,0.0),
             max_velocity: 50.0,
             acceleration_speed: 5.0,
             deceleration_unit: 20.0, //bad name. velocity -= velocity/decel_unit
             running: false,
+            on_ground: false,
+            noclip: false,
+            step_height: STEP_HEIGHT,
+            hitbox: (min, max),
+            coyote: 0,
+            jump_buffer: 0,
+            hurt_cooldown: 0,
             sprite,
         }
     }
 
-    //needs benchmark probably causing lag
-    //there has to be a better way to do this
+    ///puts the player back at coords with full health and no momentum
+    pub fn respawn(&mut self, coords: (isize, isize)) {
+        self.health = MAX_HEALTH;
+        self.last_damage = None;
+        self.hurt_cooldown = 0;
+        self.coords = coords;
+        self.last_coords = coords;
+        self.velocity = (0.0,0.0);
+        self.coyote = 0;
+        self.jump_buffer = 0;
+    }
+
+    ///coords that stand the player with the middle of its feet on a world coord
+    pub fn standing_on(&self, feet: (isize, isize)) -> (isize, isize) {
+        let ((x0, y0), (x1, _)) = self.hitbox;
+        (feet.0 - (x0 + x1)/2, feet.1 - y0)
+    }
+
+    ///world coords of the bottom left and top right corners of the players hitbox
+    pub fn bounds(&self) -> ((isize, isize), (isize, isize)) {
+        let ((x0, y0), (x1, y1)) = self.hitbox;
+        ((self.coords.0 + x0, self.coords.1 + y0), (self.coords.0 + x1, self.coords.1 + y1))
+    }
+
     pub fn update_location(&mut self, world: &World, chunk_dim: (usize, usize)) {
-        let tx = self.coords.0 + self.velocity.0 as isize/10;                           //calculate target x
-        let ty = (self.coords.1 + self.velocity.1 as isize/10)-15;                      //calculate target y
+        self.last_coords = self.coords;
+        if self.noclip {                                                                //fly straight through everything
+            self.coords.0 += self.velocity.0 as isize/10;
+            self.coords.1 += self.velocity.1 as isize/10;
+            self.velocity.0 -= self.velocity.0/self.deceleration_unit;
+            self.velocity.1 -= self.velocity.1/self.deceleration_unit;
+            self.on_ground = false;
+            return;
+        }
 
-        let mut xrange: Vec<isize> = {                                                  //gets the range of movement on x axis
-            if  tx > self.coords.0 {(self.coords.0..tx).collect()}                      //if moving positive returns movement range
-            else {(tx..self.coords.0).rev().collect()}                                  //if moving negative then construct positive range and reverses it
-        };
-        let mut yrange: Vec<isize> = {
-            if ty > self.coords.1 {(self.coords.1..ty).collect()}                       //gets range of movement on y axis
-            else {(ty..self.coords.1).rev().collect()}                                  //if neg movement then constructs positive range and reverses
+        let (min, max) = self.bounds();
+        let middle: Vec<(isize, isize)> = (min.1..max.1+1).map(|y| ((min.0 + max.0)/2, y)).collect();
+        let (submerged, liquid) = world.submersion(&middle, chunk_dim);                 //check how deep in liquid player is down its middle
+        let buoyancy = match liquid {                                                   //liquid pushes back up against gravity
+            Some(l) => GRAVITY * submerged * l.density as f32 / PLAYER_DENSITY,
+            None => 0.0,
         };
+        self.velocity.1 = (self.velocity.1 - GRAVITY + buoyancy).max(-TERMINAL_VELOCITY);
 
-        if yrange.len() > xrange.len() {                                                //if y movement bigger than x movement
-            for _ in 0..yrange.len()-xrange.len() {xrange.push(tx)}                     //pad x to len of y
-        }
-        else if xrange.len() > yrange.len() {                                           //if x movement bigger than y movement
-            for _ in 0..xrange.len()-yrange.len() {yrange.push(ty)}                     //pad y to len of x
+        if self.jump_buffer > 0 && (self.on_ground || self.coyote > 0) {               //jump if pressed recently and grounded recently
+            self.velocity.1 = JUMP_VELOCITY;
+            self.jump_buffer = 0;
+            self.coyote = 0;
         }
+        self.jump_buffer = self.jump_buffer.saturating_sub(1);
 
-        for (wx,wy) in xrange.iter().zip(yrange) {                                      //iterate through coord pairs in range of movement
-            if !world.check_collision(self.sprite.get_hitbox((*wx,wy)), chunk_dim) {    //if player hitbox of current sprite doesnt collide
-                self.coords.0 = *wx;
-                self.coords.1 = wy;
+        let dx = self.velocity.0 as isize/10;                                          //x and y move separately so a wall doesnt stop a fall
+        for _ in 0..dx.abs() {                                                          //walk 1px at a time
+            let (min, max) = self.bounds();
+            let step = if self.on_ground {self.step_height} else {0};                   //only step up bumps while walking
+            let fits = |h: isize| world.sweep_aabb(min, max, (0, h), chunk_dim).normal.is_none()                  //room to rise h
+                && world.sweep_aabb((min.0, min.1+h), (max.0, max.1+h), (dx.signum(), 0), chunk_dim).normal.is_none();   //then move over
+            match (0..step+1).find(|&h| fits(h)) {                                      //lowest height that fits
+                Some(h) => self.coords = (self.coords.0 + dx.signum(), self.coords.1 + h),
+                None => {self.velocity.0 = 0.0; break},                                 //wall too tall to step up
             }
         }
 
+        let (min, max) = self.bounds();
+        let sweep = world.sweep_aabb(min, max, (0, self.velocity.1 as isize/10), chunk_dim);
+        self.coords.1 += sweep.travelled.1;
+        if sweep.normal == Some((0, 1)) && -self.velocity.1 > SAFE_FALL_VELOCITY {      //landed hard
+            self.hurt(((-self.velocity.1 - SAFE_FALL_VELOCITY) * FALL_DAMAGE) as usize, Damage::Fall);
+        }
+        if sweep.normal.is_some() {self.velocity.1 = 0.0}                               //hit a floor or ceiling
+
+        let (min, max) = self.bounds();
+        let below = |y| world.aabb_overlaps((min.0, y), (max.0, y), chunk_dim);         //if anything is in a row under the feet
+        if self.on_ground && dx != 0 && self.velocity.1 <= 0.0 && !below(min.1-1) {     //walked off a bump or down a slope
+            let sweep = world.sweep_aabb(min, max, (0, -self.step_height), chunk_dim);
+            if sweep.normal.is_some() {self.coords.1 += sweep.travelled.1}              //stick to the ground if its close
+        }
+
+        self.on_ground = below(self.bounds().0.1-1);                                    //something right under the feet
+        if self.on_ground {self.coyote = COYOTE_TICKS}
+        else {self.coyote = self.coyote.saturating_sub(1)}
+
         self.velocity.0 -= self.velocity.0/self.deceleration_unit;
-        self.velocity.1 -= self.velocity.1/self.deceleration_unit;
+        if let Some(l) = liquid {                                                       //liquid slows player down
+            self.velocity.0 -= self.velocity.0 * l.drag * submerged;
+            self.velocity.1 -= self.velocity.1 * l.drag * submerged;
+        }
+    }
+
+    ///hurts the player for touching hazards or being buried in terrain, and remembers respawn points it touches
+    pub fn update_health(&mut self, world: &World, chunk_dim: (usize, usize)) {
+        self.hurt_cooldown = self.hurt_cooldown.saturating_sub(1);
+        if self.noclip {return}
+        let (min, max) = self.bounds();
+        let middle = (min.0 + max.0)/2;
+        let touching = (min.0-1..max.0+2).map(|x| (x, min.1-1))                        //ring just outside the hitbox
+            .chain((min.0-1..max.0+2).map(|x| (x, max.1+1)))
+            .chain((min.1..max.1+1).map(|y| (min.0-1, y)))
+            .chain((min.1..max.1+1).map(|y| (max.0+1, y)))
+            .chain((min.1..max.1+1).map(|y| (middle, y)));                              //and down the middle for what the player is in
+        let mut hazard: Option<&Material> = None;
+        for coords in touching {
+            let material = match world.get_particle(coords, chunk_dim) {Some(p) => world.registry.get(p.material()), None => continue};
+            if material.damage > hazard.map_or(0, |h| h.damage) {hazard = Some(material)}   //worst thing touched
+            if material.respawn {self.bed = Some(coords)}
+        }
+        let head = (middle, max.1 - (max.1 - min.1)/4);                                 //buried once terrain covers the middle of the head
+        let buried = match world.get_particle(head, chunk_dim) {
+            Some(p) => world.registry.get(p.material()).collision,
+            None => false,                                                              //unloaded isnt terrain yet
+        };
+
+        if self.hurt_cooldown > 0 {return}
+        let damage = match hazard {
+            Some(h) => Some((h.damage as usize, Damage::Hazard(h.id))),
+            None if buried => Some((SUFFOCATION_DAMAGE, Damage::Suffocation)),
+            None => None,
+        };
+        if let Some((amount, cause)) = damage {
+            self.hurt(amount, cause);
+            self.hurt_cooldown = HURT_INTERVAL;
+        }
+    }
+
+    ///takes health away, remembering what did it. noclip cant be hurt
+    pub fn hurt(&mut self, amount: usize, cause: Damage) {
+        if self.noclip || amount == 0 {return}
+        self.health = self.health.saturating_sub(amount);
+        self.last_damage = Some(cause);
     }
 
     pub fn walk(&mut self, direction: Direction) {
@@ -91,7 +254,26 @@ impl Player {
         }
     }
 
+    ///hurts and throws the player away from an explosion, both falling off with distance from its center
+    pub fn blast(&mut self, center: (isize, isize), radius: isize, power: f32) {
+        let (w, h) = self.sprite.get_dimensions();
+        let (dx, dy) = ((self.coords.0 + w/2 - center.0) as f32, (self.coords.1 - h/2 - center.1) as f32);  //from blast to middle of player
+        let distance = (dx*dx + dy*dy).sqrt();
+        let falloff = 1.0 - distance/(radius as f32 * BLAST_REACH);
+        if falloff <= 0.0 {return}                                                      //too far away to feel it
+        self.hurt((power * BLAST_DAMAGE * falloff) as usize, Damage::Explosion);
+        let (nx, ny) = if distance > 0.0 {(dx/distance, dy/distance)} else {(0.0, 1.0)};    //straight up if its right on top of the player
+        self.velocity.0 += nx * power * BLAST_KNOCKBACK * falloff;
+        self.velocity.1 += ny * power * BLAST_KNOCKBACK * falloff;
+    }
+
+    ///asks to jump. happens once the player is on the ground, or was very recently
     pub fn jump(&mut self) {
-        self.velocity.1+=300.0;
+        self.jump_buffer = JUMP_BUFFER_TICKS;
+    }
+
+    ///letting go of jump while still rising cuts the jump short
+    pub fn release_jump(&mut self) {
+        if self.velocity.1 > 0.0 && !self.noclip {self.velocity.1 *= JUMP_CUT}
     }
 }
\ No newline at end of file
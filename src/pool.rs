@@ -0,0 +1,99 @@
+use std::collections::HashSet;
+use std::hash::BuildHasherDefault;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
+use crate::gen::*;
+use crate::generator::WorldGenerator;
+use crate::save::Regions;
+
+///chunks waiting for a worker and where the player is so the nearest go first
+struct Queue {
+    jobs: Vec<(isize, isize)>,
+    focus: (isize, isize),
+    shutdown: bool,
+}
+
+///worker threads that load saved chunks or generate them in the background
+///chunks are requested from the main loop and handed back over a channel once ready
+pub struct ChunkPool {
+    queue: Arc<(Mutex<Queue>, Condvar)>,
+    results: mpsc::Receiver<Chunk>,
+    pending: HashSet<(isize, isize), BuildHasherDefault<ChunkHasher>>,     //requested and not yet handed back
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl ChunkPool {
+    ///starts threads workers loading chunks from saved if they were saved, otherwise generating them with generator
+    pub fn new(threads: usize, generator: Arc<dyn WorldGenerator>, saved: Option<Regions>, chunk_dim: (usize, usize)) -> Self {
+        let queue = Arc::new((Mutex::new(Queue {jobs: Vec::new(), focus: (0,0), shutdown: false}), Condvar::new()));
+        let (sender, results) = mpsc::channel();
+        let workers = (0..threads.max(1)).map(|_| {
+            let (queue, sender, generator, saved) = (queue.clone(), sender.clone(), generator.clone(), saved.clone());
+            thread::spawn(move || loop {
+                let job = {
+                    let (lock, ready) = &*queue;
+                    let mut q = lock.lock().unwrap();
+                    while q.jobs.is_empty() && !q.shutdown {q = ready.wait(q).unwrap()}          //sleep until theres work
+                    if q.shutdown {return}
+                    let focus = q.focus;
+                    let nearest = (0..q.jobs.len()).min_by_key(|&i| {let (x, y) = q.jobs[i]; (x-focus.0).pow(2) + (y-focus.1).pow(2)}).unwrap();
+                    q.jobs.swap_remove(nearest)
+                };
+                let loaded = saved.as_ref().and_then(|s| s.load_chunk(job, chunk_dim).unwrap_or_else(|e| {eprintln!("couldn't load chunk {:?}: {}", job, e); None}));
+                let chunk = loaded.unwrap_or_else(|| generator.gen_chunk(job, chunk_dim));     //gen it if it was never saved
+                if sender.send(chunk).is_err() {return}                                         //world is gone
+            })
+        }).collect();
+        Self {
+            queue,
+            results,
+            pending: HashSet::default(),
+            workers,
+        }
+    }
+
+    ///queues a chunk to be generated if it isnt already
+    pub fn request(&mut self, chunk_coords: (isize, isize)) {
+        if !self.pending.insert(chunk_coords) {return}
+        let (lock, ready) = &*self.queue;
+        lock.lock().unwrap().jobs.push(chunk_coords);
+        ready.notify_one();
+    }
+
+    ///moves the focus chunks are prioritised around and drops queued chunks keep returns false for
+    pub fn set_focus(&mut self, focus: (isize, isize), keep: impl Fn((isize, isize)) -> bool) {
+        let mut q = self.queue.0.lock().unwrap();
+        q.focus = focus;
+        let pending = &mut self.pending;
+        q.jobs.retain(|&c| keep(c) || {pending.remove(&c); false});
+    }
+
+    ///if a chunk is queued or being generated
+    #[inline]
+    pub fn is_pending(&self, chunk_coords: (isize, isize)) -> bool {
+        self.pending.contains(&chunk_coords)
+    }
+
+    ///every chunk queued or being generated
+    pub fn pending(&self) -> impl Iterator<Item = &(isize, isize)> {
+        self.pending.iter()
+    }
+
+    ///takes every chunk finished since last call without waiting
+    pub fn finished(&mut self) -> Vec<Chunk> {
+        let chunks: Vec<Chunk> = self.results.try_iter().collect();
+        for c in &chunks {self.pending.remove(&c.chunk_coords);}
+        chunks
+    }
+}
+
+impl Drop for ChunkPool {
+    fn drop(&mut self) {
+        let (lock, ready) = &*self.queue;
+        lock.lock().unwrap().shutdown = true;
+        ready.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();                                                              //finishes the chunk its on first
+        }
+    }
+}
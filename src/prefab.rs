@@ -0,0 +1,119 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use crate::gen::*;
+use crate::material::*;
+
+///where a prefab gets placed
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Placement {
+    Surface,
+    Cave,
+}
+
+#[derive(Deserialize)]
+struct PrefabDef {
+    name: String,
+    image: String,
+    placement: Placement,
+    spacing: usize,
+    chance: f64,
+    #[serde(default)]
+    sink: isize,
+    #[serde(default)]
+    min_depth: isize,
+    #[serde(default)]
+    biomes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct PrefabFile {
+    colors: HashMap<String, String>,
+    prefab: Vec<PrefabDef>,
+}
+
+
+
+///a structure painted in a png that generators stamp into terrain
+pub struct Prefab {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Option<MaterialId>>,     //row major from the top, none where terrain is left alone
+    pub placement: Placement,
+    pub spacing: isize,                 //px between placement attempts
+    pub chance: f64,                    //chance each attempt places it
+    pub sink: isize,                    //px the bottom sits under the surface
+    pub min_depth: isize,               //px under the surface caves need to be
+    pub biomes: Vec<String>,            //biomes it shows up in, empty for any
+}
+
+impl Prefab {
+    ///loads every prefab listed in a toml file, mapping image colors to registry materials
+    pub fn load_all(path: &str, registry: &Registry) -> Result<Vec<Prefab>, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("couldn't read prefab file {}: {}", path, e))?;
+        let file: PrefabFile = toml::from_str(&text).map_err(|e| format!("couldn't parse prefab file {}: {}", path, e))?;
+        let mut colors = HashMap::new();
+        for (hex, name) in &file.colors {
+            let rgb = u32::from_str_radix(hex.trim_start_matches('#'), 16).map_err(|_| format!("bad prefab color {}", hex))?;
+            let material = registry.id(name).ok_or_else(|| format!("prefab color {} uses unknown material {}", hex, name))?;
+            colors.insert([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8], material);
+        }
+
+        file.prefab.into_iter().map(|def| {
+            if def.spacing == 0 {return Err(format!("prefab {} needs a spacing above 0", def.name))}
+            let img = image::open(&def.image).map_err(|e| format!("couldn't load prefab image {}: {}", def.image, e))?.to_rgba8();
+            let cells = img.pixels().map(|p| match p.0 {
+                [_, _, _, 0] => Ok(None),                                                       //transparent leaves terrain alone
+                [r, g, b, _] => colors.get(&[r, g, b]).copied().map(Some).ok_or_else(|| format!("prefab {} has unmapped color #{:02x}{:02x}{:02x}", def.name, r, g, b)),
+            }).collect::<Result<Vec<_>, String>>()?;
+            Ok(Prefab {
+                width: img.width() as usize,
+                height: img.height() as usize,
+                cells,
+                placement: def.placement,
+                spacing: def.spacing as isize,
+                chance: def.chance,
+                sink: def.sink,
+                min_depth: def.min_depth,
+                biomes: def.biomes,
+            })
+        }).collect()
+    }
+
+    ///world coords of the top left pixel when the bottom middle pixel sits at anchor
+    pub fn origin(&self, anchor: (isize, isize)) -> (isize, isize) {
+        (anchor.0 - self.width as isize/2, anchor.1 + self.height as isize - 1)
+    }
+
+    ///writes the part of the prefab that overlaps a chunk into it, leaving any particle keep returns true for
+    pub fn stamp(&self, chunk: &mut Chunk, origin: (isize, isize), chunk_dim: (usize, usize), keep: impl Fn(Particle) -> bool) {
+        let (left, top) = (chunk.chunk_coords.0*chunk_dim.0 as isize, chunk.chunk_coords.1*chunk_dim.1 as isize);   //world coords of chunk top left
+        let rows = (top - origin.1).max(0)..(top - origin.1 + self.height as isize).min(chunk_dim.1 as isize);         //inner rows and columns the prefab covers
+        let columns = (origin.0 - left).max(0)..(origin.0 - left + self.width as isize).min(chunk_dim.0 as isize);
+        for iy in rows {
+            let py = (iy - (top - origin.1)) as usize;                                          //prefab row of inner row
+            for ix in columns.clone() {
+                let px = (ix - (origin.0 - left)) as usize;
+                if let Some(material) = self.cells[py*self.width+px] {
+                    let i = iy as usize*chunk_dim.0 + ix as usize;
+                    if keep(chunk.data[i]) {continue}
+                    chunk.data[i] = Particle::new(material, (left + ix, top - iy));
+                }
+            }
+        }
+    }
+}
+
+
+
+///cheap hash of a placement cell so the same seed always picks the same structures
+///returns a value for the roll and one for the offset within the cell
+pub fn cell_hash(seed: u32, salt: usize, cell: (isize, isize)) -> (f64, u64) {
+    let mut h = (seed as u64 ^ (salt as u64).wrapping_mul(0xff51_afd7_ed55_8ccd))
+        .wrapping_add((cell.0 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+        .wrapping_add((cell.1 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f));
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    ((h & 0xffff) as f64 / 65536.0, h >> 16)
+}
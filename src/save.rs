@@ -0,0 +1,275 @@
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use crate::gen::*;
+use crate::generator::GeneratorKind;
+use crate::material::MAX_MATERIALS;
+
+pub const FORMAT_VERSION: u16 = 1;         //bump whenever the region or meta layout changes
+const REGION_MAGIC: &[u8;4] = b"UGRG";
+pub const REGION_DIM: isize = 8;           //regions hold REGION_DIM x REGION_DIM chunks
+const META_FILE: &str = "world.toml";
+pub const SAVES_DIR: &str = "saves";        //every world gets its own save directory in here
+const LEGACY_CHUNK_SIZE: usize = 256;      //chunk size of saves from before it was configurable
+
+//region file layout, all little endian
+//    magic: [u8;4]
+//    version: u16
+//    chunk_dim: (u32, u32)
+//    zlib compressed:
+//        chunk count: u32
+//        per chunk:
+//            chunk_coords: (i64, i64)
+//            particles: [u16; w*h]
+//            has_state: u8
+//            if has_state: [(temperature: i16, lifetime: u16); w*h]
+
+
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Format(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "save io error: {}", e),
+            SaveError::Format(s) => write!(f, "bad save data: {}", s),
+        }
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(e: std::io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+
+
+///what gets stored next to the region files
+#[derive(Serialize, Deserialize)]
+struct WorldMeta {
+    version: u16,
+    seed: u32,
+    #[serde(default)]
+    generator: Option<String>,     //saves from before generators were selectable are perlin
+    #[serde(default)]
+    chunk_size: Option<usize>,     //regions only load with the chunk size they were written with
+}
+
+///modified chunks of one region read from or waiting to be written to disk
+#[derive(Default)]
+struct Region {
+    chunks: ChunkMap<Chunk>,
+    dirty: bool,
+}
+
+///the region files of a save and the regions read or written around loaded chunks, so loading chunks doesnt reread them
+///cheap to clone and shared with the workers that load chunks in the background
+#[derive(Clone)]
+pub struct Regions {
+    path: PathBuf,
+    cache: Arc<Mutex<ChunkMap<Region>>>,
+    materials: usize,                   //how many materials the registry has, particles of any other id wont load
+}
+
+impl Regions {
+    fn path(&self, region: (isize, isize)) -> PathBuf {
+        self.path.join(format!("r.{}.{}.region", region.0, region.1))
+    }
+
+    ///runs f on a region, reading it from disk first if it isnt cached. the cache isnt locked while reading
+    fn with<T>(&self, region: (isize, isize), chunk_dim: (usize, usize), f: impl FnOnce(&mut Region) -> T) -> Result<T, SaveError> {
+        if let Some(cached) = self.cache.lock().unwrap().get_mut(&region) {return Ok(f(cached))}
+        let path = self.path(region);
+        let loaded = if path.exists() {read_region(&fs::read(path)?, chunk_dim, self.materials)?} else {Region::default()};
+        Ok(f(self.cache.lock().unwrap().entry(region).or_insert(loaded)))          //keep whatever got stored while reading
+    }
+
+    ///gets a saved chunk if it was ever modified
+    pub fn load_chunk(&self, chunk_coords: (isize, isize), chunk_dim: (usize, usize)) -> Result<Option<Chunk>, SaveError> {
+        self.with(SaveDir::get_region_coords(chunk_coords), chunk_dim, |region| region.chunks.get(&chunk_coords).map(|c| {
+            let mut chunk = c.clone();
+            chunk.active = true;                                        //let anything that was moving carry on
+            chunk.modified = false;
+            chunk
+        }))
+    }
+}
+
+
+
+///a world save directory holding the seed and region files of every modified chunk
+///chunks that were never modified aren't stored since they regenerate the same from the seed
+pub struct SaveDir {
+    path: PathBuf,
+    pub seed: Option<u32>,         //seed of the saved world if there is one
+    pub generator: Option<String>, //generator of the saved world if it recorded one
+    pub chunk_size: Option<usize>, //chunk size the regions were written with
+    regions: Regions,
+}
+
+impl SaveDir {
+    ///opens a save directory, creating it if needed
+    pub fn open(path: &str) -> Result<Self, SaveError> {
+        let path = PathBuf::from(path);
+        fs::create_dir_all(&path)?;
+        let meta_path = path.join(META_FILE);
+        let (seed, generator, chunk_size) = if meta_path.exists() {
+            let meta: WorldMeta = toml::from_str(&fs::read_to_string(&meta_path)?).map_err(|e| SaveError::Format(e.to_string()))?;
+            if meta.version > FORMAT_VERSION {return Err(SaveError::Format(format!("save version {} is newer than {}", meta.version, FORMAT_VERSION)))}
+            (Some(meta.seed), Some(meta.generator.unwrap_or_else(|| GeneratorKind::Perlin.to_string())), Some(meta.chunk_size.unwrap_or(LEGACY_CHUNK_SIZE)))
+        } else {(None, None, None)};
+        Ok(Self {
+            seed,
+            generator,
+            chunk_size,
+            regions: Regions {path: path.clone(), cache: Arc::default(), materials: MAX_MATERIALS},
+            path,
+        })
+    }
+
+    ///names of the saved worlds in a directory, sorted
+    pub fn list(dir: &str) -> Vec<String> {
+        let mut worlds: Vec<String> = match fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(|e| e.ok())
+                .filter(|e| e.path().join(META_FILE).exists())                         //a world thats never been saved has nothing to load
+                .filter_map(|e| e.file_name().into_string().ok())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        worlds.sort();
+        worlds
+    }
+
+    ///gets the region a chunk belongs to
+    pub fn get_region_coords(chunk_coords: (isize, isize)) -> (isize, isize) {
+        (chunk_coords.0.div_euclid(REGION_DIM), chunk_coords.1.div_euclid(REGION_DIM))
+    }
+
+    ///refuses to load regions holding particles of material ids the registry doesnt have, like after materials.toml lost one
+    pub fn set_materials(&mut self, count: usize) {
+        self.regions.materials = count;
+    }
+
+    ///the region files, for loading chunks off the main thread
+    pub fn regions(&self) -> Regions {
+        self.regions.clone()
+    }
+
+    ///gets a saved chunk if it was ever modified
+    pub fn load_chunk(&self, chunk_coords: (isize, isize), chunk_dim: (usize, usize)) -> Result<Option<Chunk>, SaveError> {
+        self.regions.load_chunk(chunk_coords, chunk_dim)
+    }
+
+    ///queues a chunk to be written on next flush
+    pub fn store_chunk(&mut self, chunk: Chunk, chunk_dim: (usize, usize)) -> Result<(), SaveError> {
+        self.regions.with(SaveDir::get_region_coords(chunk.chunk_coords), chunk_dim, |region| {
+            region.chunks.insert(chunk.chunk_coords, chunk);
+            region.dirty = true;
+        })
+    }
+
+    ///drops cached regions keep returns false for, unless they have changes waiting to be written
+    ///the world keeps any region a loaded or loading chunk is in, so a worker never reads one thats about to change
+    pub fn drop_regions(&mut self, keep: impl Fn((isize, isize)) -> bool) {
+        self.regions.cache.lock().unwrap().retain(|coords, region| region.dirty || keep(*coords));
+    }
+
+    ///writes the world meta and every dirty region
+    pub fn flush(&mut self, seed: u32, generator: &GeneratorKind, chunk_dim: (usize, usize)) -> Result<(), SaveError> {
+        let meta = toml::to_string(&WorldMeta {version: FORMAT_VERSION, seed, generator: Some(generator.to_string()), chunk_size: Some(chunk_dim.0)}).map_err(|e| SaveError::Format(e.to_string()))?;
+        fs::write(self.path.join(META_FILE), meta)?;
+        self.seed = Some(seed);
+        self.generator = Some(generator.to_string());
+        self.chunk_size = Some(chunk_dim.0);
+        for (coords, region) in self.regions.cache.lock().unwrap().iter_mut().filter(|(_, r)| r.dirty) {
+            let path = self.regions.path(*coords);
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, write_region(region, chunk_dim)?)?;                //write then rename so a crash cant leave half a region
+            fs::rename(&tmp, path)?;
+            region.dirty = false;
+        }
+        Ok(())
+    }
+}
+
+
+
+fn write_region(region: &Region, chunk_dim: (usize, usize)) -> Result<Vec<u8>, SaveError> {
+    let mut out = Vec::new();
+    out.extend_from_slice(REGION_MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&(chunk_dim.0 as u32).to_le_bytes());
+    out.extend_from_slice(&(chunk_dim.1 as u32).to_le_bytes());
+
+    let mut body = ZlibEncoder::new(out, Compression::default());
+    body.write_all(&(region.chunks.len() as u32).to_le_bytes())?;
+    for chunk in region.chunks.values() {
+        body.write_all(&(chunk.chunk_coords.0 as i64).to_le_bytes())?;
+        body.write_all(&(chunk.chunk_coords.1 as i64).to_le_bytes())?;
+        body.write_all(&chunk.data.iter().flat_map(|p| p.0.to_le_bytes().to_vec()).collect::<Vec<u8>>())?;
+        match &chunk.state {
+            Some(state) => {
+                body.write_all(&[1])?;
+                body.write_all(&state.iter().flat_map(|s| {
+                    let mut b = s.temperature.to_le_bytes().to_vec();
+                    b.extend_from_slice(&s.lifetime.to_le_bytes());
+                    b
+                }).collect::<Vec<u8>>())?;
+            },
+            None => body.write_all(&[0])?,
+        }
+    }
+    Ok(body.finish()?)
+}
+
+fn read_region(bytes: &[u8], chunk_dim: (usize, usize), materials: usize) -> Result<Region, SaveError> {
+    if bytes.len() < 14 || &bytes[0..4] != REGION_MAGIC {return Err(SaveError::Format("not a region file".to_string()))}
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version > FORMAT_VERSION {return Err(SaveError::Format(format!("region version {} is newer than {}", version, FORMAT_VERSION)))}
+    let dim = (u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]) as usize, u32::from_le_bytes([bytes[10], bytes[11], bytes[12], bytes[13]]) as usize);
+    if dim != chunk_dim {return Err(SaveError::Format(format!("region chunk size {:?} doesnt match {:?}", dim, chunk_dim)))}
+
+    let mut body = ZlibDecoder::new(&bytes[14..]);
+    let mut read = |len: usize| -> Result<Vec<u8>, SaveError> {
+        let mut buf = vec![0; len];
+        body.read_exact(&mut buf)?;
+        Ok(buf)
+    };
+    let cells = chunk_dim.0*chunk_dim.1;
+    let count = u32::from_le_bytes(read(4)?[..].try_into().unwrap());
+    let mut region = Region::default();
+    for _ in 0..count {
+        let coords = read(16)?;
+        let chunk_coords = (i64::from_le_bytes(coords[0..8].try_into().unwrap()) as isize, i64::from_le_bytes(coords[8..16].try_into().unwrap()) as isize);
+        let data: Vec<Particle> = read(cells*2)?.chunks_exact(2).map(|b| Particle(u16::from_le_bytes([b[0], b[1]]))).collect();
+        if let Some(p) = data.iter().find(|p| p.material() as usize >= materials) {
+            return Err(SaveError::Format(format!("chunk {:?} has material id {} but there are only {} materials", chunk_coords, p.material(), materials)))
+        }
+        let state = match read(1)?[0] {
+            0 => None,
+            _ => Some(read(cells*4)?.chunks_exact(4).map(|b| CellState {
+                temperature: i16::from_le_bytes([b[0], b[1]]),
+                lifetime: u16::from_le_bytes([b[2], b[3]]),
+            }).collect()),
+        };
+        region.chunks.insert(chunk_coords, Chunk {
+            chunk_coords,
+            data,
+            state,
+            collision: Vec::new(),                  //rebuilt when the world takes the chunk
+            active: true,
+            modified: false,
+        });
+    }
+    Ok(region)
+}
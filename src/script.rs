@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use crate::config::{Config, VIEW_DISTANCES};
+use crate::inventory::INVENTORY_SLOTS;
+
+///everything a player can hold down in one tick. read from the keyboard or played back from a script
+///actions that happen once fire on the tick a control goes from released to held
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Controls {
+    pub left: bool,
+    pub right: bool,
+    pub up: bool,                       //noclip only
+    pub down: bool,                     //noclip only
+    pub jump: bool,
+    pub run: bool,
+    pub dig: bool,
+    pub place: bool,
+    pub explode: bool,
+    pub next_slot: bool,
+    pub prev_slot: bool,
+    pub grow_brush: bool,
+    pub shrink_brush: bool,
+    pub noclip: bool,
+    pub respawn: bool,                  //sent by the death screen rather than a key, does nothing while alive
+    pub slot: u8,                       //hotbar slot picked by number, 1 to 9 and 0 for none
+    pub scroll: isize,                  //hotbar slots scrolled this tick, down the hotbar is positive
+    pub swap: Option<(u8, u8)>,         //inventory slots to swap this tick, from the inventory screen
+    pub view_distance: u8,              //new view distance from the settings menu, 0 for no change
+    pub cursor: (isize, isize),         //screen coords of the mouse
+}
+
+impl Controls {
+    ///controls held now that werent held last tick
+    pub fn pressed(&self, last: &Controls) -> Controls {
+        Controls {
+            left: self.left && !last.left,
+            right: self.right && !last.right,
+            up: self.up && !last.up,
+            down: self.down && !last.down,
+            jump: self.jump && !last.jump,
+            run: self.run && !last.run,
+            dig: self.dig && !last.dig,
+            place: self.place && !last.place,
+            explode: self.explode && !last.explode,
+            next_slot: self.next_slot && !last.next_slot,
+            prev_slot: self.prev_slot && !last.prev_slot,
+            grow_brush: self.grow_brush && !last.grow_brush,
+            shrink_brush: self.shrink_brush && !last.shrink_brush,
+            noclip: self.noclip && !last.noclip,
+            respawn: self.respawn,
+            slot: if self.slot != last.slot {self.slot} else {0},
+            scroll: self.scroll,
+            swap: self.swap,
+            view_distance: self.view_distance,
+            cursor: self.cursor,
+        }
+    }
+
+    ///every button by the name scripts use for it
+    fn buttons(&mut self) -> [(&'static str, &mut bool); 15] {
+        [
+            ("left", &mut self.left),
+            ("right", &mut self.right),
+            ("up", &mut self.up),
+            ("down", &mut self.down),
+            ("jump", &mut self.jump),
+            ("run", &mut self.run),
+            ("dig", &mut self.dig),
+            ("place", &mut self.place),
+            ("explode", &mut self.explode),
+            ("next_slot", &mut self.next_slot),
+            ("prev_slot", &mut self.prev_slot),
+            ("grow_brush", &mut self.grow_brush),
+            ("shrink_brush", &mut self.shrink_brush),
+            ("noclip", &mut self.noclip),
+            ("respawn", &mut self.respawn),
+        ]
+    }
+
+    ///holds a button by name, false if theres no button called that
+    fn set(&mut self, name: &str) -> bool {
+        match self.buttons().iter_mut().find(|(n, _)| *n == name) {
+            Some((_, held)) => {**held = true; true},
+            None => false,
+        }
+    }
+}
+
+
+
+///controls to hold for a number of ticks each, one line per step:
+///    <ticks> [control ...] [slot=n] [scroll=n] [swap=a,b] [view_distance=n] [cursor=x,y]
+///e.g. "30 right jump" holds right and jump for 30 ticks. the cursor stays put until moved and # starts a comment
+#[derive(Clone, Default)]
+pub struct Script {
+    steps: Vec<(usize, Controls)>,
+}
+
+impl Script {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("couldn't read script {}: {}", path, e))?;
+        Script::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut steps = Vec::new();
+        let mut cursor = (0, 0);
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {continue}
+            let mut words = line.split_whitespace();
+            let ticks = words.next().unwrap_or("").parse().map_err(|_| format!("line {} has to start with a number of ticks", n+1))?;
+            let mut controls = Controls::default();
+            for word in words {
+                if let Some(coords) = word.strip_prefix("cursor=") {
+                    let mut xy = coords.split(',').map(|v| v.parse::<isize>());
+                    cursor = match (xy.next(), xy.next(), xy.next()) {
+                        (Some(Ok(x)), Some(Ok(y)), None) => (x, y),
+                        _ => return Err(format!("line {} has a bad cursor {}", n+1, coords)),
+                    };
+                } else if let Some(slot) = word.strip_prefix("slot=") {
+                    controls.slot = slot.parse().ok().filter(|s| *s <= 9).ok_or_else(|| format!("line {} has a bad slot {}", n+1, slot))?;
+                } else if let Some(scroll) = word.strip_prefix("scroll=") {
+                    controls.scroll = scroll.parse().map_err(|_| format!("line {} has a bad scroll {}", n+1, scroll))?;
+                } else if let Some(distance) = word.strip_prefix("view_distance=") {
+                    controls.view_distance = distance.parse().ok().filter(|d| VIEW_DISTANCES.contains(&(*d as isize))).ok_or_else(|| format!("line {} has a bad view distance {}", n+1, distance))?;
+                } else if let Some(slots) = word.strip_prefix("swap=") {
+                    let mut ab = slots.split(',').map(|v| v.parse::<u8>().ok().filter(|s| (*s as usize) < INVENTORY_SLOTS));
+                    controls.swap = match (ab.next(), ab.next(), ab.next()) {
+                        (Some(Some(a)), Some(Some(b)), None) => Some((a, b)),
+                        _ => return Err(format!("line {} has a bad swap {}", n+1, slots)),
+                    };
+                } else if !controls.set(word) {
+                    return Err(format!("line {} has unknown control {}", n+1, word));
+                }
+            }
+            controls.cursor = cursor;
+            steps.push((ticks, controls));
+        }
+        Ok(Self {steps})
+    }
+
+    ///adds a tick to the end, lengthening the last step if nothing changed
+    pub fn push(&mut self, controls: Controls) {
+        match self.steps.last_mut() {
+            Some((ticks, last)) if *last == controls => *ticks += 1,
+            _ => self.steps.push((1, controls)),
+        }
+    }
+
+    ///how many ticks the whole script lasts
+    pub fn ticks(&self) -> usize {
+        self.steps.iter().map(|(ticks, _)| ticks).sum()
+    }
+
+    ///controls held on a tick, nothing once the script runs out
+    pub fn get(&self, tick: usize) -> Controls {
+        let mut start = 0;
+        for (ticks, controls) in &self.steps {
+            if tick < start + ticks {return *controls}
+            start += ticks;
+        }
+        Controls::default()
+    }
+}
+
+impl std::fmt::Display for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (ticks, controls) in &self.steps {
+            let mut controls = *controls;
+            let cursor = controls.cursor;
+            write!(f, "{}", ticks)?;
+            for (name, held) in controls.buttons().iter() {
+                if **held {write!(f, " {}", name)?}
+            }
+            if controls.slot != 0 {write!(f, " slot={}", controls.slot)?}
+            if controls.scroll != 0 {write!(f, " scroll={}", controls.scroll)?}
+            if let Some((a, b)) = controls.swap {write!(f, " swap={},{}", a, b)?}
+            if controls.view_distance != 0 {write!(f, " view_distance={}", controls.view_distance)?}
+            writeln!(f, " cursor={},{}", cursor.0, cursor.1)?;
+        }
+        Ok(())
+    }
+}
+
+
+
+///a recorded session. the seed and generator the world was made with, the settings that change how it plays, then the script of every tick played
+///    seed <n>
+///    generator <kind>
+///    resolution <w>x<h>
+///    chunk_size <n>
+///    view_distance <n>
+///    debug <true|false>
+///    <script lines>
+///replays from before the settings were recorded leave them out and get the defaults they were made with
+pub struct Replay {
+    pub seed: u32,
+    pub generator: String,
+    pub screen_dim: (usize, usize),     //the cursor is turned into world coords through a camera centered on the screen
+    pub chunk_dim: (usize, usize),
+    pub gen_range: isize,               //which chunks are loaded and simulating
+    pub debug: bool,                    //if noclip could be toggled
+    pub script: Script,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("couldn't read replay {}: {}", path, e))?;
+        Replay::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut header = HashMap::new();
+        let mut lines = text.lines().peekable();
+        while let Some((key, value)) = lines.peek().copied().and_then(|l| l.trim().split_once(' ')) {
+            if key.starts_with(|c: char| c.is_ascii_digit()) {break}                                //header ends at the first line of script
+            header.insert(key, value.trim());
+            lines.next();
+        }
+        let defaults = Config::default();
+        let seed = header.get("seed").ok_or("replay has to start with a seed line")?.parse().map_err(|_| "replay seed has to be a number".to_string())?;
+        let generator = header.get("generator").ok_or("replay has to have a generator line")?.to_string();
+        let screen_dim = match header.get("resolution").map(|r| r.split_once('x').map(|(w, h)| (w.parse(), h.parse()))) {
+            Some(Some((Ok(w), Ok(h)))) => (w, h),
+            Some(_) => return Err("replay resolution has to be like 1280x720".to_string()),
+            None => defaults.screen_dim,
+        };
+        let chunk_size = match header.get("chunk_size") {
+            Some(size) => size.parse().map_err(|_| "replay chunk_size has to be a number".to_string())?,
+            None => defaults.chunk_dim.0,
+        };
+        let gen_range = match header.get("view_distance") {
+            Some(distance) => distance.parse().map_err(|_| "replay view_distance has to be a number".to_string())?,
+            None => defaults.gen_range,
+        };
+        let debug = match header.get("debug") {
+            Some(debug) => debug.parse().map_err(|_| "replay debug has to be true or false".to_string())?,
+            None => defaults.debug,
+        };
+        let script = Script::parse(&lines.collect::<Vec<&str>>().join("\n")).map_err(|e| format!("{} after the header", e))?;
+        Ok(Self {seed, generator, screen_dim, chunk_dim: (chunk_size, chunk_size), gen_range, debug, script})
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_string()).map_err(|e| format!("couldn't write replay {}: {}", path, e))
+    }
+
+    ///config to play the replay back with, the recorded settings over the ones given
+    pub fn config(&self, config: &Config) -> Result<Config, String> {
+        let config = Config {screen_dim: self.screen_dim, chunk_dim: self.chunk_dim, gen_range: self.gen_range, debug: self.debug, ..config.clone()};
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl std::fmt::Display for Replay {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "seed {}\ngenerator {}", self.seed, self.generator)?;
+        writeln!(f, "resolution {}x{}\nchunk_size {}\nview_distance {}\ndebug {}", self.screen_dim.0, self.screen_dim.1, self.chunk_dim.0, self.gen_range, self.debug)?;
+        write!(f, "{}", self.script)
+    }
+}
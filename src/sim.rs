@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+use crate::gen::*;
+use crate::material::*;
+
+const SETTLE_RANGE: isize = 64; //how far sideways liquids look for somewhere lower to flow. surfaces settle level to within 1px per this many
+
+impl World {
+    ///runs one tick of the particle simulation over every active chunk
+    pub fn simulate(&mut self, chunk_dim: (usize, usize)) {
+        self.tick += 1;
+        let mut active: Vec<(isize, isize)> = self.data.iter().filter(|(_, c)| c.active).map(|(coords, _)| *coords).collect();
+        active.sort_by_key(|&(cx, cy)| (cy, cx));                                           //bottom chunk rows first so falling particles dont get moved twice
+        for coords in &active {
+            if let Some(c) = self.data.get_mut(coords) {c.active = false}                   //chunks stay asleep unless something moves in them
+        }
+
+        let mut moved = HashSet::new();                                                     //where particles landed this tick
+        for chunk_coords in active {
+            let mut cells = match self.data.get(&chunk_coords) {                                //get inner index of every moving particle in chunk
+                Some(c) => c.data.iter().enumerate().filter(|(_, p)| {let m = self.registry.get(p.material()); m.is_powder() || m.is_liquid()}).map(|(i, _)| i).collect::<Vec<usize>>(),
+                None => continue,
+            };
+            let flip = self.tick % 2 == 1;                                                  //alternate x direction every tick so piles stay symmetric
+            cells.sort_by_key(|&i| {                                                        //bottom rows first, x direction depending on tick
+                let (ix, iy) = World::get_inner_coords(i, chunk_dim);
+                (std::cmp::Reverse(iy), if flip {chunk_dim.0-ix} else {ix})
+            });
+            for i in cells {
+                let coords = World::get_world_coords(chunk_coords, i, chunk_dim);                  //world coords of particle
+                if moved.contains(&coords) {continue}                                       //already moved this tick
+                let material = match self.get_particle(coords, chunk_dim) {Some(p) => self.registry.get(p.material()), None => continue};
+                let target = if material.is_liquid() {self.step_liquid(material, coords, chunk_dim)}
+                             else if material.is_powder() {self.step_powder(material, coords, chunk_dim)}
+                             else {None};                                                   //particle was swapped out for something static
+                if let Some(target) = target {
+                    let liquid = material.is_liquid();
+                    self.swap_particles(coords, target, chunk_dim);
+                    moved.insert(target);
+                    if liquid {                                                             //liquid in a sleeping chunk nearby could be looking for the gap it left
+                        self.wake((coords.0 - SETTLE_RANGE, coords.1), chunk_dim);
+                        self.wake((coords.0 + SETTLE_RANGE, coords.1), chunk_dim);
+                    }
+                }
+            }
+        }
+        self.update_debris(chunk_dim);
+    }
+
+    ///returns where the powder particle at coords moves this tick, if anywhere
+    fn step_powder(&self, material: &Material, coords: (isize, isize), chunk_dim: (usize, usize)) -> Option<(isize, isize)> {
+        let (x, y) = coords;
+        let is_empty = |c| self.can_displace(material, c, chunk_dim);
+        if is_empty((x, y-1)) {return Some((x, y-1))}                                       //fall straight down
+        let (run, drop) = material.slide;
+        let first = if (x + self.tick as isize) % 2 == 0 {1} else {-1};                     //pick which side to try first
+        for &dir in &[first, -first] {
+            if run >= drop && is_empty((x+dir, y-1)) {return Some((x+dir, y-1))}            //shallow materials always roll off diagonally
+            let clear = (1..run).all(|i| is_empty((x+dir*i, y)))                            //sideways path is clear
+                && (1..drop+1).all(|i| is_empty((x+dir*run, y-i)));                         //and the drop at the end of it is clear
+            if clear {return Some((x+dir*run, y-drop))}
+        }
+        None
+    }
+
+    ///returns where the liquid particle at coords moves this tick, if anywhere
+    ///liquids only spread sideways when there is a drop to reach or more of the same liquid pressing down on them so surfaces settle level
+    fn step_liquid(&self, material: &Material, coords: (isize, isize), chunk_dim: (usize, usize)) -> Option<(isize, isize)> {
+        let (x, y) = coords;
+        let is_empty = |c| self.can_displace(material, c, chunk_dim);
+        if is_empty((x, y-1)) {return Some((x, y-1))}                                       //fall straight down
+        let first = if (x + self.tick as isize) % 2 == 0 {1} else {-1};                     //pick which side to try first
+        for &dir in &[first, -first] {
+            if is_empty((x+dir, y-1)) {return Some((x+dir, y-1))}                           //then diagonally down
+        }
+
+        let material_at = |c| self.get_particle(c, chunk_dim).map(|p| self.registry.get(p.material()));
+        let (dispersion, interval) = material.flow;
+        if !self.tick.is_multiple_of(interval) {return None}                                //viscous liquids spread less often
+        let pressured = matches!(material_at((x, y+1)), Some(m) if m.id == material.id);    //if more of it is stacked on top, lighter liquids floating on it dont push it around
+        for &dir in &[first, -first] {
+            let mut reach = None;
+            for i in 1..SETTLE_RANGE+1 {                                                    //look sideways until blocked
+                if i > dispersion && reach.is_none() {break}                                //nowhere it could move this step anyway
+                let side = (x+dir*i, y);
+                if !is_empty(side) {                                                        //spread into open space or under lighter liquids so they end up floating
+                    if matches!(material_at(side), Some(m) if pressured && m.id == material.id) {continue}  //pressure pushes through its own liquid
+                    break
+                }
+                if i <= dispersion {reach = Some(side)}                                     //furthest it can move this step
+                if is_empty((x+dir*i, y-1)) && reach.is_some() {return reach}               //found somewhere lower to flow towards
+            }
+            if pressured && reach.is_some() {return reach}                                  //pushed out as far as it could go
+        }
+        None
+    }
+
+    ///if a particle of material could move into coords, pushing lighter liquids out of the way. unloaded chunks count as full
+    fn can_displace(&self, material: &Material, coords: (isize, isize), chunk_dim: (usize, usize)) -> bool {
+        match self.get_particle(coords, chunk_dim).map(|p| self.registry.get(p.material())) {
+            Some(target) => target.id == AIR || (target.is_liquid() && target.density < material.density),
+            None => false,
+        }
+    }
+}
+
+
@@ -0,0 +1,404 @@
+use engine::{drawing, sprite};
+use crate::bindings::{Action, Bindings, Input};
+use crate::config::{self, Config};
+use crate::inventory::{Inventory, Item, Stack, HOTBAR_SLOTS, INVENTORY_SLOTS};
+use crate::material::Registry;
+use crate::save::{SaveDir, SAVES_DIR};
+
+const HEADING_SIZE: f32 = 64.0;
+const TEXT_SIZE: f32 = 32.0;
+const ROW_HEIGHT: usize = 40;           //px between menu items
+const MENU_WIDTH: usize = 480;          //clickable width of menu items
+const TEXT_COLOR: [u8; 4] = [255,255,255,0];
+const SELECTED_COLOR: [u8; 4] = [255,200,60,0];
+const HINT_COLOR: [u8; 4] = [150,150,150,0];
+const SLOT_SIZE: usize = 48;            //px square of an inventory slot
+const SLOT_GAP: usize = 8;
+const SLOT_COLOR: [u8; 4] = [30,30,34,255];
+const HELD_COLOR: [u8; 4] = [120,170,255,255];
+const FPS_CAPS: [u64; 5] = [30, 60, 120, 144, 240];
+const MAX_SEED_LENGTH: usize = 32;
+
+///what menus read from the keyboard and mouse in one update. built by bindings::Bindings::menu_input
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct MenuInput {
+    pub up: bool,                       //all pressed this update, not held
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub confirm: bool,
+    pub back: bool,
+    pub inventory: bool,
+    pub click: bool,
+    pub cursor: (isize, isize),         //screen coords of the mouse
+    pub typed: String,                  //letters, numbers and spaces typed
+    pub erase: bool,                    //backspace
+    pub pressed: Option<Input>,         //any key or mouse button pressed, for rebinding
+}
+
+///a column of items picked with up/down and confirm, or by clicking one
+///lists longer than the screen scroll to keep the selection on it
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Menu {
+    pub selected: usize,
+}
+
+impl Menu {
+    ///moves the selection, returns the item picked this update if one was
+    fn update(&mut self, items: usize, input: &MenuInput, screen_dim: (usize, usize)) -> Option<usize> {
+        if input.up {self.selected = (self.selected + items - 1) % items}
+        if input.down {self.selected = (self.selected + 1) % items}
+        if input.click {                                                                //pick whatever is under the mouse
+            let (left, top) = items_origin(screen_dim);
+            let (x, y) = (input.cursor.0 - left as isize, input.cursor.1 - top as isize);
+            let row = self.first_row(screen_dim) + y.max(0) as usize/ROW_HEIGHT;
+            if x >= 0 && x < MENU_WIDTH as isize && y >= 0 && row < items {
+                self.selected = row;
+                return Some(self.selected);
+            }
+        }
+        if input.confirm {Some(self.selected.min(items - 1))} else {None}
+    }
+
+    ///index of the item drawn at the top of the list
+    fn first_row(&self, screen_dim: (usize, usize)) -> usize {
+        let rows = (screen_dim.1.saturating_sub(items_origin(screen_dim).1)/ROW_HEIGHT).saturating_sub(1).max(1);   //rows that fit with one spare at the bottom
+        self.selected.saturating_sub(rows - 1)
+    }
+
+    fn draw(&self, screen: &mut drawing::Screen, heading: &str, items: &[String]) {
+        let (left, top) = items_origin((screen.width, screen.height));
+        screen.draw_text((left, top - 2*ROW_HEIGHT), heading, HEADING_SIZE, &TEXT_COLOR, drawing::DEBUG_FONT);
+        let first = self.first_row((screen.width, screen.height));
+        for (i, item) in items.iter().enumerate().skip(first) {
+            let y = top + (i - first)*ROW_HEIGHT;
+            if y + ROW_HEIGHT > screen.height {break}
+            let (text, color) = if i == self.selected {(format!("> {}", item), &SELECTED_COLOR)} else {(format!("  {}", item), &TEXT_COLOR)};
+            screen.draw_text((left, y), &text, TEXT_SIZE, color, drawing::DEBUG_FONT);
+        }
+    }
+}
+
+///screen coords of the first menu item, a quarter of the way down and centered
+fn items_origin(screen_dim: (usize, usize)) -> (usize, usize) {
+    (screen_dim.0.saturating_sub(MENU_WIDTH)/2, screen_dim.1/4 + 2*ROW_HEIGHT)
+}
+
+///slot of the inventory grid at screen coords, if theres one there
+fn slot_at(coords: (isize, isize), screen_dim: (usize, usize)) -> Option<usize> {
+    let (left, top) = items_origin(screen_dim);
+    let (x, y) = (coords.0 - left as isize, coords.1 - top as isize);
+    let pitch = (SLOT_SIZE + SLOT_GAP) as isize;
+    let (column, row) = (x.div_euclid(pitch) as usize, y.div_euclid(pitch) as usize);
+    if x < 0 || y < 0 || column >= HOTBAR_SLOTS || row >= INVENTORY_SLOTS/HOTBAR_SLOTS {return None}
+    Some(row*HOTBAR_SLOTS + column)
+}
+
+///fills a box on screen, cut off at the edges
+pub fn fill_rect(screen: &mut drawing::Screen, coords: (usize, usize), dims: (usize, usize), color: [u8; 4]) {
+    for y in coords.1..(coords.1 + dims.1).min(screen.height) {
+        for x in coords.0..(coords.0 + dims.0).min(screen.width) {
+            let i = (y*screen.width + x)*4;
+            screen.buf[i..i+4].copy_from_slice(&color);
+        }
+    }
+}
+
+///draws a slot and what it holds, with a colored border if it has one
+fn draw_slot(screen: &mut drawing::Screen, coords: (usize, usize), stack: Option<Stack>, border: Option<[u8; 4]>, registry: &Registry) {
+    fill_rect(screen, coords, (SLOT_SIZE, SLOT_SIZE), border.unwrap_or(SLOT_COLOR));
+    fill_rect(screen, (coords.0+3, coords.1+3), (SLOT_SIZE-6, SLOT_SIZE-6), SLOT_COLOR);
+    if let Some(stack) = stack {
+        fill_rect(screen, (coords.0+10, coords.1+10), (SLOT_SIZE-20, SLOT_SIZE-20), stack.item.color(registry));
+        if let Item::Tool(tool, left) = stack.item {                                                      //durability left along the bottom
+            let worn = (SLOT_SIZE-12) * left as usize / tool.durability() as usize;
+            fill_rect(screen, (coords.0+6, coords.1+SLOT_SIZE-9), (worn, 3), [80,220,80,255]);
+        }
+        if stack.count > 1 {screen.draw_text((coords.0+4, coords.1+SLOT_SIZE-18), &stack.count.to_string(), 16.0, &TEXT_COLOR, drawing::DEBUG_FONT)}
+    }
+}
+
+///draws the hotbar along the bottom of the screen with the slot in hand picked out
+pub fn draw_hotbar(screen: &mut drawing::Screen, inventory: &Inventory, registry: &Registry) {
+    let left = screen.width.saturating_sub(HOTBAR_SLOTS*(SLOT_SIZE + SLOT_GAP))/2;
+    let top = screen.height - SLOT_SIZE - 20;
+    for i in 0..HOTBAR_SLOTS {
+        let border = if i == inventory.selected {Some([255,200,60,255])} else {None};
+        draw_slot(screen, (left + i*(SLOT_SIZE + SLOT_GAP), top), inventory.slots[i], border, registry);
+    }
+}
+
+///darkens everything drawn so far so menus stand out over the world
+fn dim(screen: &mut drawing::Screen) {
+    screen.buf.chunks_exact_mut(4).for_each(|p| p[..3].iter_mut().for_each(|c| *c /= 3));
+}
+
+
+
+///one screen of the game. they stack so going back returns to whatever was under
+#[derive(Clone, PartialEq, Debug)]
+pub enum State {
+    MainMenu(Menu),
+    WorldSelect(Menu, Vec<String>),     //names of the worlds in SAVES_DIR
+    NewWorld(String),                   //seed typed so far
+    Playing,
+    Paused(Menu),
+    Settings(Menu),
+    GameOver(Menu, String),             //how the player died
+    Inventory(usize, Option<usize>),    //slot the cursor is on and the slot picked up to move
+    Controls(Menu, bool),               //every action and its bindings, true while waiting for an input to bind to the selected one
+}
+
+///what a state wants done after an update. anything that needs the world is left to the game loop
+#[derive(Clone, PartialEq, Debug)]
+pub enum Transition {
+    None,
+    Push(State),
+    Pop,
+    Open(String),                       //play the saved world with this name
+    Create(Option<u32>),                //play a new world, random seed if none
+    Respawn,
+    SwapSlots(usize, usize),            //move a stack in the players inventory
+    SaveConfig,
+    Bind(Action, Input),                //add an input to an action and write the controls file
+    Unbind(Action),                     //clear an action and write the controls file
+    ToMenu,                             //save the world and go back to the main menu
+    Quit,
+}
+
+impl State {
+    ///handles a frame of input. settings are changed in place, everything else is asked for through the transition
+    pub fn update(&mut self, input: &MenuInput, config: &mut Config) -> Transition {
+        match self {
+            State::MainMenu(menu) => update_main_menu(menu, input, config),
+            State::WorldSelect(menu, worlds) => update_world_select(menu, worlds, input, config),
+            State::NewWorld(seed) => update_new_world(seed, input, config),
+            State::Playing if input.back => Transition::Push(State::Paused(Menu::default())),
+            State::Playing if input.inventory => Transition::Push(State::Inventory(0, None)),
+            State::Playing => Transition::None,
+            State::Paused(menu) => update_paused(menu, input, config),
+            State::Settings(menu) => update_settings(menu, input, config),
+            State::GameOver(menu, _) => update_game_over(menu, input, config),
+            State::Inventory(cursor, held) => update_inventory(cursor, held, input, config),
+            State::Controls(menu, waiting) => update_controls(menu, waiting, input, config),
+        }
+    }
+
+    ///draws the state over whatever is already on screen, the world if one is open
+    ///title and sprite are shown on the main menu, the inventory is the players if theres a world open
+    pub fn draw(&self, screen: &mut drawing::Screen, config: &Config, bindings: &Bindings, title: &str, sprite: &sprite::Sprite, inventory: Option<(&Inventory, &Registry)>) {
+        if *self != State::Playing {dim(screen)}
+        match self {
+            State::MainMenu(menu) => {
+                let (left, top) = items_origin((screen.width, screen.height));
+                screen.draw_sprite(sprite, ((left + MENU_WIDTH) as isize, top as isize));
+                menu.draw(screen, title, &main_menu_items());
+            },
+            State::WorldSelect(menu, worlds) => {
+                let mut items = worlds.clone();
+                items.push("Back".to_string());
+                menu.draw(screen, "Load World", &items);
+                if worlds.is_empty() {draw_hint(screen, 2, "No saved worlds yet")}
+            },
+            State::NewWorld(seed) => {
+                Menu::default().draw(screen, "New World", &[format!("Seed: {}_", seed)]);
+                let hint = match (seed.trim().is_empty(), config.seed) {
+                    (false, _) => format!("Seed {}", config::parse_seed(seed.trim())),
+                    (true, Some(s)) => format!("Blank uses the configured seed {}", s),
+                    (true, None) => "Blank picks a random seed".to_string(),
+                };
+                draw_hint(screen, 2, &hint);
+                draw_hint(screen, 3, "Type a number or any text, enter to create");
+            },
+            State::Playing => {},
+            State::Paused(menu) => menu.draw(screen, "Paused", &paused_items()),
+            State::Settings(menu) => menu.draw(screen, "Settings", &settings_items(config)),
+            State::GameOver(menu, cause) => {
+                menu.draw(screen, "You Died", &game_over_items());
+                draw_hint(screen, game_over_items().len() + 1, cause);
+            },
+            State::Inventory(cursor, held) => {
+                Menu::default().draw(screen, "Inventory", &[]);
+                let (inventory, registry) = match inventory {Some(i) => i, None => return};
+                let (left, top) = items_origin((screen.width, screen.height));
+                for i in 0..INVENTORY_SLOTS {
+                    let border = if Some(i) == *held {Some(HELD_COLOR)} else if i == *cursor {Some([255,200,60,255])} else {None};
+                    let coords = (left + (i % HOTBAR_SLOTS)*(SLOT_SIZE + SLOT_GAP), top + (i / HOTBAR_SLOTS)*(SLOT_SIZE + SLOT_GAP));
+                    draw_slot(screen, coords, inventory.slots[i], border, registry);
+                }
+                if let Some(stack) = inventory.slots[*cursor] {                          //name whats under the cursor below the grid
+                    let rows = INVENTORY_SLOTS/HOTBAR_SLOTS * (SLOT_SIZE + SLOT_GAP);
+                    let text = format!("{} x{}", stack.item.name(registry), stack.count);
+                    screen.draw_text((left, top + rows + 10), &text, TEXT_SIZE, &TEXT_COLOR, drawing::DEBUG_FONT);
+                }
+            },
+            State::Controls(menu, waiting) => {
+                let mut items = controls_items(bindings);
+                if *waiting {items[menu.selected] = format!("{}: press a key or button", action_label(action_at(menu.selected)))}
+                menu.draw(screen, "Controls", &items);
+                let (left, top) = items_origin((screen.width, screen.height));
+                screen.draw_text((left, top - ROW_HEIGHT), "Enter adds a binding, Backspace clears them", TEXT_SIZE, &HINT_COLOR, drawing::DEBUG_FONT);
+            },
+        }
+    }
+}
+
+///greyed out text on a menu row
+fn draw_hint(screen: &mut drawing::Screen, row: usize, text: &str) {
+    let (left, top) = items_origin((screen.width, screen.height));
+    screen.draw_text((left, top + row*ROW_HEIGHT), text, TEXT_SIZE, &HINT_COLOR, drawing::DEBUG_FONT);
+}
+
+
+
+fn main_menu_items() -> Vec<String> {
+    vec!["Load World".to_string(), "New World".to_string(), "Settings".to_string(), "Quit".to_string()]
+}
+
+fn update_main_menu(menu: &mut Menu, input: &MenuInput, config: &mut Config) -> Transition {
+    match menu.update(main_menu_items().len(), input, config.screen_dim) {
+        Some(0) => Transition::Push(State::WorldSelect(Menu::default(), SaveDir::list(SAVES_DIR))),
+        Some(1) => Transition::Push(State::NewWorld(String::new())),
+        Some(2) => Transition::Push(State::Settings(Menu::default())),
+        Some(_) => Transition::Quit,
+        None if input.back => Transition::Quit,
+        None => Transition::None,
+    }
+}
+
+fn update_world_select(menu: &mut Menu, worlds: &[String], input: &MenuInput, config: &mut Config) -> Transition {
+    match menu.update(worlds.len() + 1, input, config.screen_dim) {                 //every world then back
+        Some(i) if i < worlds.len() => Transition::Open(worlds[i].clone()),
+        Some(_) => Transition::Pop,
+        None if input.back => Transition::Pop,
+        None => Transition::None,
+    }
+}
+
+fn update_new_world(seed: &mut String, input: &MenuInput, config: &mut Config) -> Transition {
+    if input.erase {seed.pop();}
+    seed.extend(input.typed.chars().take(MAX_SEED_LENGTH - seed.len().min(MAX_SEED_LENGTH)));
+    if input.back {return Transition::Pop}
+    if !input.confirm {return Transition::None}
+    match seed.trim() {
+        "" => Transition::Create(config.seed),
+        text => Transition::Create(Some(config::parse_seed(text))),
+    }
+}
+
+fn paused_items() -> Vec<String> {
+    vec!["Resume".to_string(), "Settings".to_string(), "Save and Quit to Menu".to_string(), "Save and Quit Game".to_string()]
+}
+
+fn update_paused(menu: &mut Menu, input: &MenuInput, config: &mut Config) -> Transition {
+    match menu.update(paused_items().len(), input, config.screen_dim) {
+        Some(0) => Transition::Pop,
+        Some(1) => Transition::Push(State::Settings(Menu::default())),
+        Some(2) => Transition::ToMenu,
+        Some(_) => Transition::Quit,
+        None if input.back => Transition::Pop,
+        None => Transition::None,
+    }
+}
+
+fn settings_items(config: &Config) -> Vec<String> {
+    vec![
+        format!("FPS Cap: {}", config.fps),
+        format!("View Distance: {}", config.gen_range),
+        format!("Debug: {}", if config.debug {"On"} else {"Off"}),
+        "Controls".to_string(),
+        "Save Settings".to_string(),
+        "Back".to_string(),
+    ]
+}
+
+///changes settings in place with left/right, confirming a setting steps it forward
+fn update_settings(menu: &mut Menu, input: &MenuInput, config: &mut Config) -> Transition {
+    let picked = menu.update(settings_items(config).len(), input, config.screen_dim);
+    let step = if input.right || picked.is_some() {1} else if input.left {-1} else {0};
+    match (menu.selected, picked) {
+        (0, _) if step != 0 => {                                                        //next cap up or down the list
+            let fps = config.fps;
+            config.fps = if step > 0 {*FPS_CAPS.iter().find(|f| **f > fps).unwrap_or(&FPS_CAPS[0])}
+                         else {*FPS_CAPS.iter().rev().find(|f| **f < fps).unwrap_or(&FPS_CAPS[FPS_CAPS.len()-1])};
+        },
+        (1, _) => config.gen_range = (config.gen_range + step).max(*config::VIEW_DISTANCES.start()).min(*config::VIEW_DISTANCES.end()),
+        (2, _) if step != 0 => config.debug = !config.debug,
+        (3, Some(_)) => return Transition::Push(State::Controls(Menu::default(), false)),
+        (4, Some(_)) => return Transition::SaveConfig,
+        (5, Some(_)) => return Transition::Pop,
+        _ => {},
+    }
+    if input.back {Transition::Pop} else {Transition::None}
+}
+
+///moves around the grid, confirming or clicking picks a slot up then puts it down on another
+fn update_inventory(cursor: &mut usize, held: &mut Option<usize>, input: &MenuInput, config: &mut Config) -> Transition {
+    if input.left {*cursor = (*cursor + INVENTORY_SLOTS - 1) % INVENTORY_SLOTS}
+    if input.right {*cursor = (*cursor + 1) % INVENTORY_SLOTS}
+    if input.up {*cursor = (*cursor + INVENTORY_SLOTS - HOTBAR_SLOTS) % INVENTORY_SLOTS}
+    if input.down {*cursor = (*cursor + HOTBAR_SLOTS) % INVENTORY_SLOTS}
+    let clicked = if input.click {slot_at(input.cursor, config.screen_dim)} else {None};
+    if let Some(slot) = clicked {*cursor = slot}
+    if input.back || input.inventory {return Transition::Pop}
+    if !input.confirm && clicked.is_none() {return Transition::None}
+    match held.take() {
+        Some(from) => Transition::SwapSlots(from, *cursor),
+        None => {
+            *held = Some(*cursor);
+            Transition::None
+        },
+    }
+}
+
+///the action on a row of the controls menu. rows past the last action are back
+fn action_at(row: usize) -> Action {
+    Action::all().nth(row).unwrap_or(Action::Pause)
+}
+
+///how an action is named in the controls menu, move_left is Move left
+fn action_label(action: Action) -> String {
+    let name = action.name().replace('_', " ");
+    name[..1].to_uppercase() + &name[1..]
+}
+
+fn controls_items(bindings: &Bindings) -> Vec<String> {
+    let mut items: Vec<String> = Action::all().map(|action| {
+        let inputs = bindings.get(action).iter().map(|i| i.to_string()).collect::<Vec<String>>();
+        format!("{}: {}", action_label(action), if inputs.is_empty() {"none".to_string()} else {inputs.join(", ")})
+    }).collect();
+    items.push("Back".to_string());
+    items
+}
+
+///picking an action waits for the next key or mouse button and binds it, whatever pause is bound to cancels
+fn update_controls(menu: &mut Menu, waiting: &mut bool, input: &MenuInput, config: &mut Config) -> Transition {
+    let actions = Action::all().count();
+    if *waiting {
+        if input.back {*waiting = false}
+        else if let Some(pressed) = input.pressed {
+            *waiting = false;
+            return Transition::Bind(action_at(menu.selected), pressed);
+        }
+        return Transition::None;
+    }
+    if input.erase && menu.selected < actions {return Transition::Unbind(action_at(menu.selected))}
+    match menu.update(actions + 1, input, config.screen_dim) {                       //every action then back
+        Some(i) if i < actions => {*waiting = true; Transition::None},
+        Some(_) => Transition::Pop,
+        None if input.back => Transition::Pop,
+        None => Transition::None,
+    }
+}
+
+fn game_over_items() -> Vec<String> {
+    vec!["Respawn".to_string(), "Save and Quit to Menu".to_string()]
+}
+
+fn update_game_over(menu: &mut Menu, input: &MenuInput, config: &mut Config) -> Transition {
+    match menu.update(game_over_items().len(), input, config.screen_dim) {
+        Some(0) => Transition::Respawn,
+        Some(_) => Transition::ToMenu,
+        None => Transition::None,
+    }
+}
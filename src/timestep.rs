@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+
+///turns real time into a whole number of fixed length ticks so the game runs the same speed at any fps
+pub struct Timestep {
+    tick: Duration,             //length of one tick
+    accumulator: Duration,      //time passed that hasnt been ticked yet
+    last: Instant,
+    max_ticks: usize,           //most ticks run to catch up at once, time past that is dropped
+}
+
+impl Timestep {
+    pub fn new(tick_rate: u32, max_ticks: usize) -> Self {
+        Self {
+            tick: Duration::from_secs(1) / tick_rate,
+            accumulator: Duration::from_secs(0),
+            last: Instant::now(),
+            max_ticks,
+        }
+    }
+
+    ///adds the time since last call and returns how many ticks are due
+    ///if the game fell too far behind the extra time is thrown away so it slows down instead of spiraling
+    pub fn advance(&mut self) -> usize {
+        let now = Instant::now();
+        self.accumulator += now - self.last;
+        self.last = now;
+        let mut ticks = 0;
+        while self.accumulator >= self.tick {
+            self.accumulator -= self.tick;
+            ticks += 1;
+        }
+        if ticks > self.max_ticks {
+            ticks = self.max_ticks;
+            self.accumulator = Duration::from_secs(0);
+        }
+        ticks
+    }
+
+    ///how far between the last tick and the next one we are from 0 to 1, for drawing things in between
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.tick.as_secs_f32()
+    }
+}
+
+///point fraction t of the way from a to b
+pub fn lerp(a: (isize, isize), b: (isize, isize), t: f32) -> (isize, isize) {
+    (a.0 + ((b.0 - a.0) as f32 * t).round() as isize, a.1 + ((b.1 - a.1) as f32 * t).round() as isize)
+}
@@ -0,0 +1,56 @@
+pub const HANDS_TIER: u8 = 0;               //digging with nothing in hand
+pub const HANDS_POWER: f32 = 0.25;
+
+///things the player digs with. higher tiers dig faster and get through materials that need them
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tool {
+    Shovel,
+    Pickaxe,
+    Drill,
+}
+
+impl Tool {
+    ///compared against the tier materials need to be dug
+    pub fn tier(&self) -> u8 {
+        match self {
+            Tool::Shovel => 1,
+            Tool::Pickaxe => 2,
+            Tool::Drill => 3,
+        }
+    }
+
+    ///hardness worth of strength built up every tick spent digging
+    pub fn power(&self) -> f32 {
+        match self {
+            Tool::Shovel => 0.5,
+            Tool::Pickaxe => 1.0,
+            Tool::Drill => 2.0,
+        }
+    }
+
+    ///half the width and height of the ellipse it digs
+    pub fn brush(&self) -> (isize, isize) {
+        match self {
+            Tool::Shovel => (10, 5),    //wide and shallow
+            Tool::Pickaxe => (5, 5),
+            Tool::Drill => (3, 12),     //narrow and deep
+        }
+    }
+
+    ///ticks of digging before it breaks
+    pub const fn durability(&self) -> u16 {
+        match self {
+            Tool::Shovel => 1800,
+            Tool::Pickaxe => 3600,
+            Tool::Drill => 7200,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Shovel => "shovel",
+            Tool::Pickaxe => "pickaxe",
+            Tool::Drill => "drill",
+        }
+    }
+}